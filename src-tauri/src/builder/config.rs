@@ -1,21 +1,75 @@
-use std::{path::PathBuf, process::Command};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    process::Command,
+};
 
 use serde::{Deserialize, Serialize};
 
-use crate::builder::swift::SwiftBin;
+use crate::builder::{
+    info_plist::{deep_merge, template_info_plist, toml_to_plist},
+    manifest::{load_package_model, PackageModel},
+    migrate::{migrate, MigrationReport},
+    sdk::installed_iphoneos_version,
+    validate::check,
+};
 
-pub const FORMAT_VERSION: u32 = 1;
+pub const FORMAT_VERSION: u32 = 2;
+// SwiftPM's default iOS deployment target when the package declares none
+pub const DEFAULT_DEPLOYMENT_TARGET: &str = "12.0";
+pub const DEVICE_FAMILIES: &[&str] = &["iphone", "ipad"];
+pub const ORIENTATIONS: &[&str] = &[
+    "portrait",
+    "portrait_upside_down",
+    "landscape_left",
+    "landscape_right",
+];
 
+// Resolved [profile.<name>] for a build, with its inherits chain applied
 pub struct BuildSettings {
+    pub profile: String,
     pub debug: bool,
+    pub swiftc_flags: Vec<String>,
+    pub linker_flags: Vec<String>,
+    pub defines: Vec<String>,
+    pub env: BTreeMap<String, String>,
+}
+
+impl BuildSettings {
+    pub fn configuration(&self) -> &'static str {
+        if self.debug {
+            "debug"
+        } else {
+            "release"
+        }
+    }
 }
 
-// TODO: Min ios version, etc.
 pub struct ProjectConfig {
+    pub package_name: String,
     pub product: String,
     pub version_num: String,
     pub version_string: String,
     pub bundle_id: String,
+    pub display_name: String,
+    pub deployment_target: String,
+    // UIDeviceFamily values, 1 = iPhone, 2 = iPad
+    pub device_family: Vec<u8>,
+    pub orientations: Vec<String>,
+    pub ipad_orientations: Vec<String>,
+    // Targets of the product that declare resources, and therefore get a resource bundle
+    pub resource_targets: Vec<String>,
+    // Paths of local binary targets, relative to the project
+    pub binary_targets: Vec<String>,
+    // [info] merged with the [profile.<name>.info] tables of the selected profile chain
+    pub info: plist::Dictionary,
+    // Values available to [[key]] placeholders in Info.plist and [info]
+    pub placeholders: HashMap<String, String>,
+    pub build: BuildSettings,
+    pub hooks: HooksConfig,
+    // Set when ycode.toml was upgraded from an older format while loading
+    pub migration: Option<MigrationReport>,
     pub project_path: PathBuf,
 }
 
@@ -23,6 +77,75 @@ pub struct ProjectConfig {
 struct TomlConfig {
     pub format_version: u32,
     pub project: ProjectTomlConfig,
+    #[serde(default, skip_serializing_if = "toml::Table::is_empty")]
+    pub info: toml::Table,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profile: BTreeMap<String, ProfileTomlConfig>,
+    #[serde(default, skip_serializing_if = "HooksConfig::is_empty")]
+    pub hooks: HooksConfig,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tasks: BTreeMap<String, TaskConfig>,
+    #[serde(skip)]
+    pub migration: Option<MigrationReport>,
+}
+
+// Shell commands run in the project directory around a build, see builder::hooks for their
+// environment
+#[derive(Deserialize, Serialize, Default, Clone)]
+pub struct HooksConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_build: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_pack: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_install: Option<String>,
+}
+
+impl HooksConfig {
+    fn is_empty(&self) -> bool {
+        self.pre_build.is_none() && self.post_pack.is_none() && self.post_install.is_none()
+    }
+}
+
+// [tasks.<name>], run with run_task
+#[derive(Deserialize, Serialize, Clone)]
+pub struct TaskConfig {
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    // Relative to the project directory, which is the default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    // Tasks run before this one, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    // Regex with named groups, see builder::tasks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub problem_matcher: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct ProfileTomlConfig {
+    // Another profile, ending in "debug" or "release". Required for custom profiles.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inherits: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub swiftc_flags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub linker_flags: Vec<String>,
+    // Swift compilation conditions, passed as -D<NAME>
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub defines: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bundle_id_suffix: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "toml::Table::is_empty")]
+    pub info: toml::Table,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -30,49 +153,438 @@ struct ProjectTomlConfig {
     pub version_num: String,
     pub version_string: String,
     pub bundle_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deployment_target: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub product: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_family: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orientations: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipad_orientations: Option<Vec<String>>,
 }
 
-// TODO: Check platforms
-#[derive(Deserialize)]
-struct SwiftPackageDump {
-    name: String,
-    targets: Vec<SwiftPackageTarget>,
+impl ProjectConfig {
+    pub fn load(project_path: PathBuf, toolchain_path: &str, profile: &str) -> Result<Self, String> {
+        let toml_config = TomlConfig::load_or_default(project_path.clone())?;
+        let package = load_package_model(&project_path, toolchain_path)?;
+        Self::resolve(project_path, toolchain_path, profile, toml_config, package)
+    }
+
+    fn resolve(
+        project_path: PathBuf,
+        toolchain_path: &str,
+        profile: &str,
+        toml_config: TomlConfig,
+        package: PackageModel,
+    ) -> Result<Self, String> {
+        let chain = profile_chain(&toml_config.profile, profile)?;
+        let raw_config = toml::Value::try_from(&toml_config)
+            .map_err(|e| format!("Failed to read ycode.toml: {}", e))?;
+
+        let product = resolve_product(toml_config.project.product, &package)?;
+        let deployment_target =
+            resolve_deployment_target(toml_config.project.deployment_target, &package)?;
+        check_platforms(
+            &package,
+            &deployment_target,
+            installed_iphoneos_version(toolchain_path).as_deref(),
+        )?;
+        let device_family = resolve_device_family(toml_config.project.device_family)?;
+        let orientations = resolve_orientations(
+            toml_config.project.orientations,
+            &["portrait", "landscape_left", "landscape_right"],
+        )?;
+        let ipad_orientations = resolve_orientations(
+            toml_config.project.ipad_orientations,
+            &["portrait", "portrait_upside_down", "landscape_left", "landscape_right"],
+        )?;
+
+        // Only the bundles `swift build --product` builds are expected in the .app
+        let resource_targets = package
+            .product_targets(&product)
+            .into_iter()
+            .filter(|t| !t.resources.is_empty())
+            .map(|t| t.name.clone())
+            .collect();
+        let binary_targets = package
+            .targets
+            .iter()
+            .filter(|t| t.target_type == "binary")
+            .filter_map(|t| t.path.clone())
+            .collect();
+
+        let mut info = toml_to_plist(toml::Value::Table(toml_config.info))
+            .into_dictionary()
+            .unwrap_or_default();
+        let mut build = BuildSettings {
+            profile: profile.to_string(),
+            debug: chain.last().map_or(true, |base| base == "debug"),
+            swiftc_flags: Vec::new(),
+            linker_flags: Vec::new(),
+            defines: Vec::new(),
+            env: BTreeMap::new(),
+        };
+        let mut bundle_id = toml_config.project.bundle_id;
+        let mut display_name = toml_config
+            .project
+            .display_name
+            .unwrap_or_else(|| product.clone());
+        // Apply the chain from the base configuration down to the selected profile
+        for name in chain.iter().rev() {
+            let Some(profile) = toml_config.profile.get(name) else {
+                continue;
+            };
+            build.swiftc_flags.extend(profile.swiftc_flags.iter().cloned());
+            build.linker_flags.extend(profile.linker_flags.iter().cloned());
+            build.defines.extend(profile.defines.iter().cloned());
+            build.env.extend(profile.env.clone());
+            if let Some(suffix) = &profile.bundle_id_suffix {
+                bundle_id.push_str(suffix);
+            }
+            if let Some(name) = &profile.display_name {
+                display_name = name.clone();
+            }
+            if let Some(profile_info) =
+                toml_to_plist(toml::Value::Table(profile.info.clone())).into_dictionary()
+            {
+                deep_merge(&mut info, profile_info);
+            }
+        }
+
+        let mut config = ProjectConfig {
+            package_name: package.name,
+            display_name,
+            product,
+            version_num: toml_config.project.version_num,
+            version_string: toml_config.project.version_string,
+            bundle_id,
+            deployment_target,
+            device_family,
+            orientations,
+            ipad_orientations,
+            resource_targets,
+            binary_targets,
+            info,
+            placeholders: HashMap::new(),
+            build,
+            hooks: toml_config.hooks,
+            migration: toml_config.migration,
+            project_path,
+        };
+
+        let mut placeholders = HashMap::new();
+        flatten_placeholders(&raw_config, "", &mut placeholders);
+        for (key, value) in [
+            ("package_name", &config.package_name),
+            ("product", &config.product),
+            ("display_name", &config.display_name),
+            ("bundle_id", &config.bundle_id),
+            ("version_num", &config.version_num),
+            ("version_string", &config.version_string),
+            ("deployment_target", &config.deployment_target),
+        ] {
+            placeholders.insert(key.to_string(), value.clone());
+        }
+        placeholders.insert(
+            "configuration".to_string(),
+            config.build.configuration().to_string(),
+        );
+        placeholders.insert("profile".to_string(), config.build.profile.clone());
+        config.placeholders = placeholders;
+
+        Ok(config)
+    }
+
+    // Sets up an existing Swift package for YCode: writes ycode.toml with the inferred product and
+    // bundle id, an Info.plist that follows the config through placeholders, and Resources/
+    pub fn init(project_path: PathBuf, toolchain_path: &str) -> Result<Self, String> {
+        if project_path.join("ycode.toml").exists() {
+            return Err("Project already has a ycode.toml".to_string());
+        }
+        if !project_path.join("Package.swift").exists() {
+            return Err(format!(
+                "No Package.swift found in {}",
+                project_path.display()
+            ));
+        }
+
+        let package = load_package_model(&project_path, toolchain_path)?;
+        // With several executables the first one is picked, ycode.toml says which so it can be changed
+        let product = package
+            .executable_products()
+            .into_iter()
+            .next()
+            .ok_or(format!(
+                "Package '{}' has no executable products to package",
+                package.name
+            ))?;
+
+        let mut toml_config = TomlConfig::default(&infer_bundle_id(&product));
+        toml_config.project.product = Some(product);
+        // Checked before anything is written, so a package YCode can't build is left untouched
+        let content = toml::to_string(&toml_config).map_err(|e| e.to_string())?;
+        check(&content)?;
+        let config = ProjectConfig::resolve(
+            project_path.clone(),
+            toolchain_path,
+            "debug",
+            toml_config,
+            package,
+        )?;
+
+        std::fs::write(project_path.join("ycode.toml"), content).map_err(|e| e.to_string())?;
+        std::fs::create_dir_all(project_path.join("Resources"))
+            .map_err(|e| format!("Failed to create Resources directory: {}", e))?;
+        let info_plist = project_path.join("Info.plist");
+        if !info_plist.exists() {
+            template_info_plist()
+                .to_file_xml(&info_plist)
+                .map_err(|e| format!("Failed to write Info.plist: {}", e))?;
+        }
+
+        Ok(config)
+    }
 }
 
-// TODO: Resources
-#[derive(Deserialize)]
-struct SwiftPackageTarget {
-    name: String,
+// "My App" -> "com.example.my-app"
+fn infer_bundle_id(product: &str) -> String {
+    let name: String = product
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let name = name.trim_matches('-');
+    format!(
+        "com.example.{}",
+        if name.is_empty() { "app" } else { name }
+    )
 }
 
-impl ProjectConfig {
-    pub fn load(project_path: PathBuf, toolchain_path: &str) -> Result<Self, String> {
-        let toml_config = TomlConfig::load_or_default(project_path.clone())?;
-        let swift = SwiftBin::new(toolchain_path)?;
-        let raw_package = swift.command()
-            .arg("package")
-            .arg("dump-package")
-            .current_dir(&project_path)
-            .output()
-            .map_err(|e| format!("Failed to execute swift command: {}", e))?;
-        if !raw_package.status.success() {
+// Returns the profile names from `profile` up to the "debug" or "release" it inherits from
+fn profile_chain(
+    profiles: &BTreeMap<String, ProfileTomlConfig>,
+    profile: &str,
+) -> Result<Vec<String>, String> {
+    let mut chain: Vec<String> = Vec::new();
+    let mut current = profile.to_string();
+    loop {
+        if chain.contains(&current) {
+            return Err(format!(
+                "Profile '{}' inherits from itself ({} -> {})",
+                profile,
+                chain.join(" -> "),
+                current
+            ));
+        }
+        chain.push(current.clone());
+        let builtin = current == "debug" || current == "release";
+        match profiles.get(&current) {
+            Some(p) if builtin && p.inherits.is_some() => {
+                return Err(format!("Profile '{}' cannot inherit from another profile", current));
+            }
+            Some(_) | None if builtin => return Ok(chain),
+            Some(p) => {
+                current = p.inherits.clone().ok_or(format!(
+                    "Profile '{}' must set `inherits` to \"debug\", \"release\" or another profile",
+                    current
+                ))?;
+            }
+            None => return Err(format!("Unknown profile '{}' in ycode.toml", current)),
+        }
+    }
+}
+
+// Every scalar in ycode.toml is addressable by its dotted path, e.g. [[project.bundle_id]]
+fn flatten_placeholders(value: &toml::Value, prefix: &str, out: &mut HashMap<String, String>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten_placeholders(value, &path, out);
+            }
+        }
+        toml::Value::String(s) => {
+            out.insert(prefix.to_string(), s.clone());
+        }
+        toml::Value::Array(_) => {}
+        other => {
+            out.insert(prefix.to_string(), other.to_string());
+        }
+    }
+}
+
+fn resolve_product(configured: Option<String>, package: &PackageModel) -> Result<String, String> {
+    let executables = package.executable_products();
+    if let Some(product) = configured {
+        if !executables.contains(&product) {
             return Err(format!(
-                "Failed to dump package: {}",
-                String::from_utf8_lossy(&raw_package.stderr)
+                "Product '{}' from ycode.toml is not an executable product of package '{}' (available: {})",
+                product,
+                package.name,
+                executables.join(", ")
             ));
         }
-        
-        let package: SwiftPackageDump = serde_json::from_slice(&raw_package.stdout)
-            .map_err(|e| format!("Failed to parse package dump: {}", e))?;
+        return Ok(product);
+    }
 
-        Ok(ProjectConfig {
-            product: package.name,
-            version_num: toml_config.project.version_num,
-            version_string: toml_config.project.version_string,
-            bundle_id: toml_config.project.bundle_id,
-            project_path,
+    match executables.len() {
+        0 => Err(format!(
+            "Package '{}' has no executable products to package",
+            package.name
+        )),
+        1 => Ok(executables[0].clone()),
+        _ => Err(format!(
+            "Package '{}' has multiple executable products ({}), set `product` under [project] in ycode.toml to choose one",
+            package.name,
+            executables.join(", ")
+        )),
+    }
+}
+
+fn resolve_deployment_target(
+    configured: Option<String>,
+    package: &PackageModel,
+) -> Result<String, String> {
+    let package_min = package
+        .platforms
+        .iter()
+        .find(|p| p.platform_name == "ios")
+        .map(|p| p.version.clone());
+
+    match (configured, package_min) {
+        (Some(target), Some(min)) => {
+            if compare_versions(&target, &min)? == Ordering::Less {
+                return Err(format!(
+                    "Deployment target {} is lower than the minimum iOS version {} declared in Package.swift",
+                    target, min
+                ));
+            }
+            Ok(target)
+        }
+        (Some(target), None) => {
+            parse_version(&target)?;
+            Ok(target)
+        }
+        (None, Some(min)) => Ok(min),
+        (None, None) => Ok(DEFAULT_DEPLOYMENT_TARGET.to_string()),
+    }
+}
+
+// Reports packages that can't be built for iOS with the installed SDK before swiftc does
+fn check_platforms(
+    package: &PackageModel,
+    deployment_target: &str,
+    sdk_version: Option<&str>,
+) -> Result<(), String> {
+    let declared: Vec<&str> = package
+        .platforms
+        .iter()
+        .map(|p| p.platform_name.as_str())
+        .collect();
+    // Without any platforms SwiftPM's defaults apply, which include iOS
+    if !declared.is_empty() && !declared.contains(&"ios") {
+        return Err(format!(
+            "Package '{}' only declares the platforms {}, add .iOS to `platforms` in Package.swift to build it for iOS",
+            package.name,
+            declared.join(", ")
+        ));
+    }
+
+    let Some(sdk_version) = sdk_version else {
+        return Ok(());
+    };
+    let package_min = package
+        .platforms
+        .iter()
+        .find(|p| p.platform_name == "ios")
+        .map(|p| p.version.as_str());
+    if let Some(min) = package_min {
+        if compare_versions(min, sdk_version)? == Ordering::Greater {
+            return Err(format!(
+                "Package.swift requires iOS {} but the installed darwin SDK is iPhoneOS {}, install the SDK from a newer Xcode or lower the iOS platform version",
+                min, sdk_version
+            ));
+        }
+    }
+    if compare_versions(deployment_target, sdk_version)? == Ordering::Greater {
+        return Err(format!(
+            "Deployment target {} is newer than the installed darwin SDK (iPhoneOS {}), install the SDK from a newer Xcode or lower deployment_target in ycode.toml",
+            deployment_target, sdk_version
+        ));
+    }
+    Ok(())
+}
+
+fn resolve_device_family(configured: Option<Vec<String>>) -> Result<Vec<u8>, String> {
+    let families = configured.unwrap_or(vec!["iphone".to_string(), "ipad".to_string()]);
+    if families.is_empty() {
+        return Err("device_family in ycode.toml must not be empty".to_string());
+    }
+    families
+        .iter()
+        .map(|family| match family.as_str() {
+            "iphone" => Ok(1),
+            "ipad" => Ok(2),
+            _ => Err(format!(
+                "Unknown device family '{}', expected \"iphone\" or \"ipad\"",
+                family
+            )),
+        })
+        .collect()
+}
+
+fn resolve_orientations(
+    configured: Option<Vec<String>>,
+    default: &[&str],
+) -> Result<Vec<String>, String> {
+    let orientations =
+        configured.unwrap_or(default.iter().map(|o| o.to_string()).collect());
+    orientations
+        .iter()
+        .map(|orientation| match orientation.as_str() {
+            "portrait" => Ok("UIInterfaceOrientationPortrait".to_string()),
+            "portrait_upside_down" => Ok("UIInterfaceOrientationPortraitUpsideDown".to_string()),
+            "landscape_left" => Ok("UIInterfaceOrientationLandscapeLeft".to_string()),
+            "landscape_right" => Ok("UIInterfaceOrientationLandscapeRight".to_string()),
+            _ => Err(format!("Unknown orientation '{}'", orientation)),
         })
+        .collect()
+}
+
+pub fn parse_version(version: &str) -> Result<Vec<u32>, String> {
+    let parts = version
+        .split('.')
+        .map(|p| p.parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| format!("Invalid version: {}", version))?;
+    if parts.is_empty() || parts.len() > 3 {
+        return Err(format!("Invalid version: {}", version));
+    }
+    Ok(parts)
+}
+
+pub fn compare_versions(a: &str, b: &str) -> Result<Ordering, String> {
+    let mut a = parse_version(a)?;
+    let mut b = parse_version(b)?;
+    a.resize(3, 0);
+    b.resize(3, 0);
+    Ok(a.cmp(&b))
+}
+
+// Tasks only need ycode.toml, so they are available without a toolchain or a Package.swift
+pub fn load_tasks(project_path: PathBuf) -> Result<BTreeMap<String, TaskConfig>, String> {
+    if !project_path.join("ycode.toml").exists() {
+        return Ok(BTreeMap::new());
     }
+    Ok(TomlConfig::load(project_path)?.tasks)
 }
 
 impl TomlConfig {
@@ -83,12 +595,23 @@ impl TomlConfig {
                 version_num: "1".to_string(),
                 version_string: "1.0.0".to_string(),
                 bundle_id: bundle_id.to_string(),
+                deployment_target: None,
+                product: None,
+                display_name: None,
+                device_family: None,
+                orientations: None,
+                ipad_orientations: None,
             },
+            info: toml::Table::new(),
+            profile: BTreeMap::new(),
+            hooks: HooksConfig::default(),
+            tasks: BTreeMap::new(),
+            migration: None,
         }
     }
 
     pub fn load_or_default(project_path: PathBuf) -> Result<Self, String> {
-        if project_path.exists() {
+        if project_path.join("ycode.toml").exists() {
             Self::load(project_path)
         } else {
             let config = Self::default("com.example.myapp");
@@ -100,13 +623,17 @@ impl TomlConfig {
     fn load(project_path: PathBuf) -> Result<Self, String> {
         let content =
             std::fs::read_to_string(project_path.join("ycode.toml")).map_err(|e| e.to_string())?;
-        let config: TomlConfig = toml::from_str(&content).map_err(|e| e.to_string())?;
-        if config.format_version != FORMAT_VERSION {
-            return Err(format!(
-                "Unsupported format version: {}, expected: {}",
-                config.format_version, FORMAT_VERSION
-            ));
-        }
+        // Syntax errors and files from a newer YCode are reported before anything is migrated
+        check(&content)?;
+        let (content, migration) = match migrate(&project_path, &content)? {
+            Some((migrated, report)) => {
+                check(&migrated)?;
+                (migrated, Some(report))
+            }
+            None => (content, None),
+        };
+        let mut config: TomlConfig = toml::from_str(&content).map_err(|e| e.to_string())?;
+        config.migration = migration;
         Ok(config)
     }
 
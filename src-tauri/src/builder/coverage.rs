@@ -0,0 +1,296 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::builder::{
+    crossplatform::{linux_path, windows_path},
+    manifest::PackageModel,
+    swift::SwiftBin,
+};
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CoverageReport {
+    pub lines_covered: u64,
+    pub lines_total: u64,
+    pub percent: f64,
+    pub files: Vec<FileCoverage>,
+    pub lcov_path: PathBuf,
+    pub cobertura_path: PathBuf,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FileCoverage {
+    // Path the editor can open, and the path relative to the project
+    pub path: String,
+    pub relative_path: String,
+    pub lines_covered: u64,
+    pub lines_total: u64,
+    pub percent: f64,
+    // Execution count of every executable line
+    pub lines: BTreeMap<u32, u64>,
+}
+
+// Exports the coverage of the last `swift test --enable-code-coverage` run with the toolchain's
+// llvm-cov and writes .ycode/coverage/{lcov.info,cobertura.xml,coverage.json}. The package's test
+// targets are left out.
+pub fn collect(
+    project_path: &Path,
+    swift_bin: &SwiftBin,
+    package: &PackageModel,
+) -> Result<CoverageReport, String> {
+    let output = swift_bin
+        .command()
+        .arg("build")
+        .arg("--show-bin-path")
+        .current_dir(project_path)
+        .output()
+        .map_err(|e| format!("Failed to execute swift command: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to find the build directory: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    let bin_path = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .last()
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+
+    let profdata = format!("{}/codecov/default.profdata", bin_path);
+    if !PathBuf::from(windows_path(&profdata)).exists() {
+        return Err("No coverage data found, run the tests with coverage enabled".to_string());
+    }
+    let test_binary = test_binary(&bin_path)?;
+
+    // Plain arguments only, on Windows they pass through the WSL shell
+    let output = swift_bin
+        .tool_command("llvm-cov")
+        .arg("export")
+        .arg("-format=lcov")
+        .arg(format!("-instr-profile={}", profdata))
+        .arg("-ignore-filename-regex=.build/")
+        .arg(&test_binary)
+        .current_dir(project_path)
+        .output()
+        .map_err(|e| format!("Failed to execute llvm-cov: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "llvm-cov export failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let project_prefix = format!(
+        "{}/",
+        linux_path(&project_path.to_string_lossy()).trim_end_matches('/')
+    );
+    let test_dirs: Vec<String> = test_directories(package)
+        .into_iter()
+        .map(|dir| format!("{}{}/", project_prefix, dir))
+        .collect();
+    let lcov = without_files(&String::from_utf8_lossy(&output.stdout), &test_dirs);
+    let files = parse_lcov(&lcov, &project_prefix);
+    let lines_covered = files.iter().map(|f| f.lines_covered).sum();
+    let lines_total = files.iter().map(|f| f.lines_total).sum();
+
+    let coverage_dir = project_path.join(".ycode").join("coverage");
+    fs::create_dir_all(&coverage_dir)
+        .map_err(|e| format!("Failed to create coverage directory: {}", e))?;
+    let report = CoverageReport {
+        lines_covered,
+        lines_total,
+        percent: percent(lines_covered, lines_total),
+        files,
+        lcov_path: coverage_dir.join("lcov.info"),
+        cobertura_path: coverage_dir.join("cobertura.xml"),
+    };
+
+    fs::write(&report.lcov_path, &lcov).map_err(|e| format!("Failed to write lcov: {}", e))?;
+    fs::write(&report.cobertura_path, cobertura(&report, project_path))
+        .map_err(|e| format!("Failed to write Cobertura report: {}", e))?;
+    let json = serde_json::to_string(&report).map_err(|e| e.to_string())?;
+    fs::write(coverage_dir.join("coverage.json"), json)
+        .map_err(|e| format!("Failed to write coverage summary: {}", e))?;
+
+    Ok(report)
+}
+
+// Source directories of the test targets, relative to the project
+fn test_directories(package: &PackageModel) -> Vec<String> {
+    package
+        .targets
+        .iter()
+        .filter(|target| target.target_type == "test")
+        .map(|target| match &target.path {
+            Some(path) => path.trim_start_matches("./").trim_end_matches('/').to_string(),
+            None => format!("Tests/{}", target.name),
+        })
+        .collect()
+}
+
+// Drops the lcov records of source files under any of `dirs`. Filtered here rather than with
+// -ignore-filename-regex, since llvm-cov only gets plain arguments.
+fn without_files(lcov: &str, dirs: &[String]) -> String {
+    let mut filtered = String::new();
+    let mut record = String::new();
+    let mut skip = false;
+    for line in lcov.lines() {
+        if let Some(file) = line.strip_prefix("SF:") {
+            skip = dirs.iter().any(|dir| file.starts_with(dir.as_str()));
+        }
+        record.push_str(line);
+        record.push('\n');
+        if line == "end_of_record" {
+            if !skip {
+                filtered.push_str(&record);
+            }
+            record.clear();
+            skip = false;
+        }
+    }
+    filtered
+}
+
+// <Package>PackageTests.xctest is an executable on Linux and a bundle on macOS
+fn test_binary(bin_path: &str) -> Result<String, String> {
+    let entries = fs::read_dir(windows_path(bin_path))
+        .map_err(|e| format!("Failed to read build directory: {}", e))?;
+    let bundle = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .find(|name| name.ends_with("PackageTests.xctest"))
+        .ok_or("No test bundle found in the build directory".to_string())?;
+
+    let path = format!("{}/{}", bin_path, bundle);
+    if PathBuf::from(windows_path(&path)).is_dir() {
+        let stem = bundle.trim_end_matches(".xctest");
+        return Ok(format!("{}/Contents/MacOS/{}", path, stem));
+    }
+    Ok(path)
+}
+
+fn parse_lcov(lcov: &str, project_prefix: &str) -> Vec<FileCoverage> {
+    let mut files = Vec::new();
+    let mut current: Option<(String, BTreeMap<u32, u64>)> = None;
+    for line in lcov.lines() {
+        if let Some(file) = line.strip_prefix("SF:") {
+            current = Some((file.to_string(), BTreeMap::new()));
+        } else if let Some(data) = line.strip_prefix("DA:") {
+            let mut parts = data.split(',');
+            if let (Some((_, lines)), Some(Ok(number)), Some(Ok(hits))) = (
+                current.as_mut(),
+                parts.next().map(|p| p.parse::<u32>()),
+                parts.next().map(|p| p.parse::<u64>()),
+            ) {
+                *lines.entry(number).or_default() += hits;
+            }
+        } else if line == "end_of_record" {
+            if let Some((file, lines)) = current.take() {
+                let lines_covered = lines.values().filter(|hits| **hits > 0).count() as u64;
+                let lines_total = lines.len() as u64;
+                files.push(FileCoverage {
+                    path: windows_path(&file),
+                    relative_path: file
+                        .strip_prefix(project_prefix)
+                        .unwrap_or(&file)
+                        .to_string(),
+                    lines_covered,
+                    lines_total,
+                    percent: percent(lines_covered, lines_total),
+                    lines,
+                });
+            }
+        }
+    }
+    files
+}
+
+fn percent(covered: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 100.0;
+    }
+    covered as f64 * 100.0 / total as f64
+}
+
+fn cobertura(report: &CoverageReport, project_path: &Path) -> String {
+    let rate = |covered: u64, total: u64| percent(covered, total) / 100.0;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    // One package per directory, one class per file
+    let mut packages: BTreeMap<String, Vec<&FileCoverage>> = BTreeMap::new();
+    for file in &report.files {
+        let dir = file
+            .relative_path
+            .rsplit_once('/')
+            .map_or(".", |(dir, _)| dir)
+            .to_string();
+        packages.entry(dir).or_default().push(file);
+    }
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" ?>\n");
+    xml.push_str(
+        "<!DOCTYPE coverage SYSTEM \"http://cobertura.sourceforge.net/xml/coverage-04.dtd\">\n",
+    );
+    xml.push_str(&format!(
+        "<coverage line-rate=\"{:.4}\" branch-rate=\"0\" lines-covered=\"{}\" lines-valid=\"{}\" branches-covered=\"0\" branches-valid=\"0\" complexity=\"0\" version=\"0\" timestamp=\"{}\">\n",
+        rate(report.lines_covered, report.lines_total),
+        report.lines_covered,
+        report.lines_total,
+        timestamp
+    ));
+    xml.push_str(&format!(
+        "  <sources>\n    <source>{}</source>\n  </sources>\n  <packages>\n",
+        xml_escape(&project_path.to_string_lossy())
+    ));
+    for (name, files) in packages {
+        let covered = files.iter().map(|f| f.lines_covered).sum();
+        let total = files.iter().map(|f| f.lines_total).sum();
+        xml.push_str(&format!(
+            "    <package name=\"{}\" line-rate=\"{:.4}\" branch-rate=\"0\" complexity=\"0\">\n      <classes>\n",
+            xml_escape(&name),
+            rate(covered, total)
+        ));
+        for file in files {
+            let class = file
+                .relative_path
+                .rsplit_once('/')
+                .map_or(file.relative_path.as_str(), |(_, name)| name);
+            xml.push_str(&format!(
+                "        <class name=\"{}\" filename=\"{}\" line-rate=\"{:.4}\" branch-rate=\"0\" complexity=\"0\">\n          <methods/>\n          <lines>\n",
+                xml_escape(class),
+                xml_escape(&file.relative_path),
+                rate(file.lines_covered, file.lines_total)
+            ));
+            for (number, hits) in &file.lines {
+                xml.push_str(&format!(
+                    "            <line number=\"{}\" hits=\"{}\"/>\n",
+                    number, hits
+                ));
+            }
+            xml.push_str("          </lines>\n        </class>\n");
+        }
+        xml.push_str("      </classes>\n    </package>\n");
+    }
+    xml.push_str("  </packages>\n</coverage>\n");
+    xml
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
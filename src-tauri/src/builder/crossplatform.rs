@@ -1,6 +1,6 @@
 #[cfg(target_os = "windows")]
 use crate::windows::{has_wsl, wsl_to_windows_path, windows_to_wsl_path};
-use std::{fs, path::PathBuf, process::{Command, Stdio}};
+use std::{collections::BTreeMap, fs, path::PathBuf, process::{Command, Stdio}};
 
 pub fn symlink(target: &str, link: &str) -> std::io::Result<()> {
     #[cfg(not(target_os = "windows"))]
@@ -94,6 +94,39 @@ pub fn linux_env(key: &str) -> Result<String, String> {
     }
 }
 
+// A shell command, run by bash in WSL on Windows. The command's current directory and variables
+// set with set_linux_envs carry over.
+pub fn linux_shell(script: &str) -> Command {
+    #[cfg(not(target_os = "windows"))]
+    {
+        let mut cmd = Command::new("bash");
+        cmd.arg("-c").arg(script);
+        return cmd;
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let mut cmd = Command::new("wsl");
+        cmd.args(["--exec", "bash", "-l", "-c"]).arg(script);
+        cmd
+    }
+}
+
+// A program run directly, or through a login shell in WSL on Windows so it is found on the
+// user's PATH there
+pub fn linux_command(program: &str) -> Command {
+    #[cfg(not(target_os = "windows"))]
+    {
+        return Command::new(program);
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let mut cmd = Command::new("wsl");
+        cmd.args(["--exec", "bash", "-l", "-c", "exec \"$0\" \"$@\""])
+            .arg(program);
+        cmd
+    }
+}
+
 pub fn windows_path(path: &str) -> String {
     #[cfg(not(target_os = "windows"))]
     {
@@ -158,4 +191,31 @@ pub fn remove_dir_all(path: &PathBuf) -> Result<(), String> {
             Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
         }
     }
-}
\ No newline at end of file
+}
+
+// Sets environment variables on a command that may be run through WSL, which only forwards
+// variables listed in WSLENV
+pub fn set_linux_envs(cmd: &mut Command, envs: &BTreeMap<String, String>) {
+    for (key, value) in envs {
+        cmd.env(key, value);
+    }
+    #[cfg(target_os = "windows")]
+    {
+        if !envs.is_empty() {
+            // Keep variables forwarded by earlier calls on the same command
+            let mut wslenv = cmd
+                .get_envs()
+                .find(|(key, _)| *key == "WSLENV")
+                .and_then(|(_, value)| value)
+                .map(|value| value.to_string_lossy().to_string())
+                .unwrap_or_else(|| std::env::var("WSLENV").unwrap_or_default());
+            for key in envs.keys() {
+                if !wslenv.is_empty() {
+                    wslenv.push(':');
+                }
+                wslenv.push_str(key);
+            }
+            cmd.env("WSLENV", wslenv);
+        }
+    }
+}
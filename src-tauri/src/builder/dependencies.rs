@@ -0,0 +1,486 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{Emitter, Window};
+
+use crate::{
+    builder::{
+        manifest::load_package_model,
+        manifest_edit::{self, identity_from_location, swift_string},
+        swift::{pipe_command, validate_toolchain, SwiftBin},
+    },
+    emit_error_and_return,
+};
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedPin {
+    pub identity: String,
+    pub location: String,
+    pub version: Option<String>,
+    pub revision: Option<String>,
+    pub branch: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "kind", content = "value", rename_all = "camelCase")]
+pub enum VersionRequirement {
+    From(String),
+    UpToNextMinor(String),
+    Exact(String),
+    Range { from: String, to: String },
+    Branch(String),
+    Revision(String),
+}
+
+impl VersionRequirement {
+    // Arguments of `swift package add-dependency`
+    fn flags(&self) -> Vec<String> {
+        let flag = |name: &str, value: &str| vec![name.to_string(), value.to_string()];
+        match self {
+            VersionRequirement::From(version) => flag("--from", version),
+            VersionRequirement::UpToNextMinor(version) => flag("--up-to-next-minor-from", version),
+            VersionRequirement::Exact(version) => flag("--exact", version),
+            VersionRequirement::Range { from, to } => {
+                [flag("--from", from), flag("--to", to)].concat()
+            }
+            VersionRequirement::Branch(branch) => flag("--branch", branch),
+            VersionRequirement::Revision(revision) => flag("--revision", revision),
+        }
+    }
+
+    // Requirement part of the `.package(url:...)` expression
+    fn manifest_argument(&self) -> String {
+        match self {
+            VersionRequirement::From(version) => format!("from: {}", swift_string(version)),
+            VersionRequirement::UpToNextMinor(version) => {
+                format!(".upToNextMinor(from: {})", swift_string(version))
+            }
+            VersionRequirement::Exact(version) => format!("exact: {}", swift_string(version)),
+            VersionRequirement::Range { from, to } => {
+                format!("{}..<{}", swift_string(from), swift_string(to))
+            }
+            VersionRequirement::Branch(branch) => format!("branch: {}", swift_string(branch)),
+            VersionRequirement::Revision(revision) => {
+                format!("revision: {}", swift_string(revision))
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetProduct {
+    pub target: String,
+    pub product: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Dependency {
+    pub identity: String,
+    pub name: String,
+    pub url: String,
+    // Local checkout (or package directory for local packages)
+    pub path: String,
+    // From Package.resolved, None for the root and local packages
+    pub version: Option<String>,
+    pub revision: Option<String>,
+    pub branch: Option<String>,
+    pub dependencies: Vec<Dependency>,
+}
+
+// Output of `swift package show-dependencies --format json`
+#[derive(Deserialize)]
+struct ShownDependency {
+    identity: String,
+    name: String,
+    url: String,
+    path: String,
+    #[serde(default)]
+    dependencies: Vec<ShownDependency>,
+}
+
+// Package.resolved pins by identity. Version 1 keeps the pins under "object" and has no identity,
+// versions 2 and 3 list them at the top level.
+pub fn read_resolved(project_path: &Path) -> Result<BTreeMap<String, ResolvedPin>, String> {
+    let path = project_path.join("Package.resolved");
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read Package.resolved: {}", e))?;
+    let json: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse Package.resolved: {}", e))?;
+
+    let pins = json
+        .get("pins")
+        .or_else(|| json.get("object").and_then(|o| o.get("pins")))
+        .and_then(|p| p.as_array())
+        .ok_or("Package.resolved has no pins".to_string())?;
+
+    let string = |value: &Value, key: &str| value.get(key).and_then(|v| v.as_str()).map(String::from);
+    let mut resolved = BTreeMap::new();
+    for pin in pins {
+        let location = string(pin, "location")
+            .or_else(|| string(pin, "repositoryURL"))
+            .unwrap_or_default();
+        let identity = string(pin, "identity").unwrap_or_else(|| identity_from_location(&location));
+        let state = pin.get("state").cloned().unwrap_or(Value::Null);
+        resolved.insert(
+            identity.clone(),
+            ResolvedPin {
+                identity,
+                location,
+                version: string(&state, "version"),
+                revision: string(&state, "revision"),
+                branch: string(&state, "branch"),
+            },
+        );
+    }
+    Ok(resolved)
+}
+
+// Runs show-dependencies and fills in the pinned versions
+pub fn dependency_tree(project_path: &Path, swift_bin: &SwiftBin) -> Result<Dependency, String> {
+    let output = swift_bin
+        .command()
+        .arg("package")
+        .arg("show-dependencies")
+        .arg("--format")
+        .arg("json")
+        .current_dir(project_path)
+        .output()
+        .map_err(|e| format!("Failed to execute swift command: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to show dependencies: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    // Fetch progress may be printed before the JSON
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let json = stdout
+        .find('{')
+        .map(|start| &stdout[start..])
+        .ok_or("show-dependencies printed no JSON".to_string())?;
+    let shown: ShownDependency = serde_json::from_str(json)
+        .map_err(|e| format!("Failed to parse dependencies: {}", e))?;
+
+    let pins = read_resolved(project_path)?;
+    Ok(with_pins(shown, &pins))
+}
+
+fn with_pins(shown: ShownDependency, pins: &BTreeMap<String, ResolvedPin>) -> Dependency {
+    let pin = pins.get(&shown.identity);
+    Dependency {
+        version: pin.and_then(|p| p.version.clone()),
+        revision: pin.and_then(|p| p.revision.clone()),
+        branch: pin.and_then(|p| p.branch.clone()),
+        identity: shown.identity,
+        name: shown.name,
+        url: shown.url,
+        path: shown.path,
+        dependencies: shown
+            .dependencies
+            .into_iter()
+            .map(|d| with_pins(d, pins))
+            .collect(),
+    }
+}
+
+pub fn describe_pin(pin: &ResolvedPin) -> String {
+    match (&pin.version, &pin.branch, &pin.revision) {
+        (Some(version), _, _) => version.clone(),
+        (None, Some(branch), Some(revision)) => {
+            format!("{} ({})", branch, &revision[..revision.len().min(7)])
+        }
+        (None, None, Some(revision)) => revision[..revision.len().min(7)].to_string(),
+        _ => "unknown".to_string(),
+    }
+}
+
+// Prints which pins were added, changed or removed by a resolve or update
+fn report_changes(
+    window: &Window,
+    before: &BTreeMap<String, ResolvedPin>,
+    after: &BTreeMap<String, ResolvedPin>,
+) {
+    let mut lines = Vec::new();
+    for (identity, pin) in after {
+        match before.get(identity) {
+            None => lines.push(format!("Resolved {} at {}", identity, describe_pin(pin))),
+            Some(old) if old != pin => lines.push(format!(
+                "Updated {} from {} to {}",
+                identity,
+                describe_pin(old),
+                describe_pin(pin)
+            )),
+            _ => {}
+        }
+    }
+    for identity in before.keys().filter(|i| !after.contains_key(*i)) {
+        lines.push(format!("Removed {}", identity));
+    }
+    if lines.is_empty() {
+        lines.push("Dependencies are up to date".to_string());
+    }
+    for line in lines {
+        window
+            .emit("build-output", line)
+            .expect("failed to send output");
+    }
+}
+
+async fn run_package_command(
+    window: &Window,
+    folder: &str,
+    toolchain_path: &str,
+    args: &[String],
+) -> Result<Dependency, String> {
+    if !validate_toolchain(toolchain_path) {
+        return emit_error_and_return(window, "Invalid Toolchain");
+    }
+    let project_path = Path::new(folder);
+    let swift_bin = SwiftBin::new(toolchain_path)?;
+    let before = read_resolved(project_path).unwrap_or_default();
+
+    let mut cmd = swift_bin.command();
+    cmd.arg("package").args(args).current_dir(folder);
+    pipe_command(&mut cmd, window, false, None).await?;
+
+    let after = match read_resolved(project_path) {
+        Ok(after) => after,
+        Err(e) => return emit_error_and_return(window, &e),
+    };
+    report_changes(window, &before, &after);
+    let tree = match dependency_tree(project_path, &swift_bin) {
+        Ok(tree) => tree,
+        Err(e) => return emit_error_and_return(window, &e),
+    };
+    window
+        .emit("build-output", "command.done.0")
+        .expect("failed to send output");
+    Ok(tree)
+}
+
+#[tauri::command]
+pub async fn resolve_packages(
+    window: Window,
+    folder: String,
+    toolchain_path: String,
+) -> Result<Dependency, String> {
+    window
+        .emit("build-output", "Resolving package dependencies...")
+        .expect("failed to send output");
+    run_package_command(&window, &folder, &toolchain_path, &["resolve".to_string()]).await
+}
+
+// Updates the given packages, or all of them when `packages` is empty
+#[tauri::command]
+pub async fn update_packages(
+    window: Window,
+    folder: String,
+    toolchain_path: String,
+    packages: Vec<String>,
+) -> Result<Dependency, String> {
+    window
+        .emit("build-output", "Updating package dependencies...")
+        .expect("failed to send output");
+    let mut args = vec!["update".to_string()];
+    args.extend(packages);
+    run_package_command(&window, &folder, &toolchain_path, &args).await
+}
+
+#[tauri::command]
+pub async fn show_dependencies(folder: String, toolchain_path: String) -> Result<Dependency, String> {
+    if !validate_toolchain(&toolchain_path) {
+        return Err("Invalid Toolchain".to_string());
+    }
+    let swift_bin = SwiftBin::new(&toolchain_path)?;
+    dependency_tree(Path::new(&folder), &swift_bin)
+}
+
+// `swift package add-dependency` and `add-target-dependency` were added in Swift 6
+fn supports_add_dependency(swift_bin: &SwiftBin, project_path: &Path) -> bool {
+    swift_bin
+        .command()
+        .arg("package")
+        .arg("add-dependency")
+        .arg("--help")
+        .current_dir(project_path)
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+async fn add_with_swiftpm(
+    window: &Window,
+    swift_bin: &SwiftBin,
+    folder: &str,
+    url: &str,
+    identity: &str,
+    requirement: &VersionRequirement,
+    target_products: &[TargetProduct],
+) -> Result<(), String> {
+    let mut cmd = swift_bin.command();
+    cmd.arg("package")
+        .arg("add-dependency")
+        .arg(url)
+        .args(requirement.flags())
+        .current_dir(folder);
+    pipe_command(&mut cmd, window, false, None).await?;
+    for target_product in target_products {
+        let mut cmd = swift_bin.command();
+        cmd.arg("package")
+            .arg("add-target-dependency")
+            .arg(&target_product.product)
+            .arg(&target_product.target)
+            .arg("--package")
+            .arg(identity)
+            .current_dir(folder);
+        pipe_command(&mut cmd, window, false, None).await?;
+    }
+    Ok(())
+}
+
+fn add_with_rewriter(
+    manifest: &str,
+    url: &str,
+    identity: &str,
+    requirement: &VersionRequirement,
+    target_products: &[TargetProduct],
+) -> Result<String, String> {
+    let dependency = format!(
+        ".package(url: {}, {})",
+        swift_string(url),
+        requirement.manifest_argument()
+    );
+    let mut manifest = manifest_edit::add_dependency(manifest, &dependency)?;
+    for target_product in target_products {
+        let product = format!(
+            ".product(name: {}, package: {})",
+            swift_string(&target_product.product),
+            swift_string(identity)
+        );
+        manifest =
+            manifest_edit::add_target_dependency(&manifest, &target_product.target, &product)?;
+    }
+    Ok(manifest)
+}
+
+// Restores Package.swift when the edit or the edited manifest fails
+fn rollback_on_error<T>(
+    window: &Window,
+    manifest_path: &Path,
+    original: &str,
+    result: Result<T, String>,
+) -> Result<T, String> {
+    result.or_else(|e| {
+        std::fs::write(manifest_path, original)
+            .map_err(|err| format!("{}\nFailed to restore Package.swift: {}", e, err))?;
+        emit_error_and_return(window, &format!("{}\nPackage.swift was restored", e))
+    })
+}
+
+#[tauri::command]
+pub async fn add_package_dependency(
+    window: Window,
+    folder: String,
+    toolchain_path: String,
+    url: String,
+    requirement: VersionRequirement,
+    target_products: Vec<TargetProduct>,
+) -> Result<(), String> {
+    if !validate_toolchain(&toolchain_path) {
+        return emit_error_and_return(&window, "Invalid Toolchain");
+    }
+    let project_path = PathBuf::from(&folder);
+    let manifest_path = project_path.join("Package.swift");
+    let original = std::fs::read_to_string(&manifest_path)
+        .map_err(|e| format!("Failed to read Package.swift: {}", e))?;
+    let identity = identity_from_location(&url);
+    if manifest_edit::has_dependency(&original, &identity).unwrap_or(false) {
+        return emit_error_and_return(
+            &window,
+            &format!("{} is already a dependency of this package", identity),
+        );
+    }
+
+    window
+        .emit("build-output", format!("Adding {}...", identity))
+        .expect("failed to send output");
+    let swift_bin = SwiftBin::new(&toolchain_path)?;
+    let result = if supports_add_dependency(&swift_bin, &project_path) {
+        add_with_swiftpm(
+            &window,
+            &swift_bin,
+            &folder,
+            &url,
+            &identity,
+            &requirement,
+            &target_products,
+        )
+        .await
+    } else {
+        add_with_rewriter(&original, &url, &identity, &requirement, &target_products).and_then(
+            |manifest| {
+                std::fs::write(&manifest_path, manifest)
+                    .map_err(|e| format!("Failed to write Package.swift: {}", e))
+            },
+        )
+    };
+    let result = result.and_then(|_| load_package_model(&project_path, &toolchain_path).map(|_| ()));
+    rollback_on_error(&window, &manifest_path, &original, result)?;
+
+    window
+        .emit(
+            "build-output",
+            format!("Added {} to Package.swift", identity),
+        )
+        .expect("failed to send output");
+    window
+        .emit("build-output", "command.done.0")
+        .expect("failed to send output");
+    Ok(())
+}
+
+// Removes a package (by URL, path or identity) and the products of it used by the targets
+#[tauri::command]
+pub async fn remove_package_dependency(
+    window: Window,
+    folder: String,
+    toolchain_path: String,
+    package: String,
+) -> Result<(), String> {
+    if !validate_toolchain(&toolchain_path) {
+        return emit_error_and_return(&window, "Invalid Toolchain");
+    }
+    let project_path = PathBuf::from(&folder);
+    let manifest_path = project_path.join("Package.swift");
+    let original = std::fs::read_to_string(&manifest_path)
+        .map_err(|e| format!("Failed to read Package.swift: {}", e))?;
+    let identity = identity_from_location(&package);
+
+    let result = manifest_edit::remove_dependency(&original, &identity)
+        .and_then(|manifest| {
+            std::fs::write(&manifest_path, manifest)
+                .map_err(|e| format!("Failed to write Package.swift: {}", e))
+        })
+        .and_then(|_| load_package_model(&project_path, &toolchain_path).map(|_| ()));
+    rollback_on_error(&window, &manifest_path, &original, result)?;
+
+    window
+        .emit(
+            "build-output",
+            format!("Removed {} from Package.swift", identity),
+        )
+        .expect("failed to send output");
+    window
+        .emit("build-output", "command.done.0")
+        .expect("failed to send output");
+    Ok(())
+}
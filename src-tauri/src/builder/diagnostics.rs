@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+use serde::Serialize;
+
+use crate::builder::crossplatform::windows_path;
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+    Note,
+    Remark,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildDiagnostic {
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    // Windows path on Windows, None for diagnostics without a location (e.g. from the linker)
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    // Source excerpt, carets and linker context printed under the diagnostic
+    pub snippet: Vec<String>,
+    pub fix_its: Vec<String>,
+    pub notes: Vec<BuildDiagnostic>,
+}
+
+enum Snippet {
+    // Nothing read after the header yet
+    Start,
+    // llvm style: the source line was read, a caret line may follow
+    Source,
+    // llvm style: the caret line was read, a fix-it line may follow
+    Caret,
+    // swift style: "12 | code" and "   |   `- error: ..." lines
+    Framed,
+    // Indented lines, e.g. ">>> referenced by" or the symbols under "Undefined symbols"
+    Indented,
+    Done,
+}
+
+// Turns swiftc, clang and linker output into diagnostics, one line at a time. Diagnostics are
+// returned once the lines that belong to them (snippet, fix-its, notes) have been read.
+pub struct DiagnosticParser {
+    located: Regex,
+    unlocated: Regex,
+    undefined_symbols: Regex,
+    framed: Regex,
+    caret: Regex,
+    progress: Regex,
+    pending: Option<BuildDiagnostic>,
+    snippet: Snippet,
+    paths: HashMap<String, String>,
+}
+
+impl DiagnosticParser {
+    pub fn new() -> Result<Self, String> {
+        let regex = |pattern: &str| Regex::new(pattern).map_err(|e| format!("Invalid regex: {}", e));
+        Ok(DiagnosticParser {
+            located: regex(
+                r"^(?P<file>[^:\s][^:]*):(?P<line>\d+):(?:(?P<column>\d+):)? (?P<severity>error|warning|note|remark): (?P<message>.*)$",
+            )?,
+            unlocated: regex(
+                r"^(?:[\w.+-]+: )?(?P<severity>error|warning|note|remark): (?P<message>.*)$",
+            )?,
+            undefined_symbols: regex(r"^Undefined symbols for architecture \S+:$")?,
+            framed: regex(r"^\s*\d*\s*\|")?,
+            caret: regex(r"^\s*[\^~][\^~ ]*$")?,
+            progress: regex(r"^\[\d+/\d+\] ")?,
+            pending: None,
+            snippet: Snippet::Done,
+            paths: HashMap::new(),
+        })
+    }
+
+    pub fn push_line(&mut self, line: &str) -> Vec<BuildDiagnostic> {
+        if self.continue_pending(line) {
+            return Vec::new();
+        }
+
+        let header = self.parse_header(line);
+        // Notes directly after an error or warning belong to it
+        if let Some(note) = header
+            .as_ref()
+            .filter(|d| d.severity == DiagnosticSeverity::Note)
+        {
+            if let Some(pending) = self
+                .pending
+                .as_mut()
+                .filter(|p| p.severity != DiagnosticSeverity::Note)
+            {
+                pending.notes.push(note.clone());
+                self.snippet = Snippet::Start;
+                return Vec::new();
+            }
+        }
+
+        let finished = self.pending.take().into_iter().collect();
+        if let Some(diagnostic) = header {
+            self.pending = Some(diagnostic);
+            self.snippet = Snippet::Start;
+        } else if self.undefined_symbols.is_match(line) {
+            self.pending = Some(BuildDiagnostic {
+                severity: DiagnosticSeverity::Error,
+                message: line.trim_end_matches(':').to_string(),
+                file: None,
+                line: None,
+                column: None,
+                snippet: Vec::new(),
+                fix_its: Vec::new(),
+                notes: Vec::new(),
+            });
+            self.snippet = Snippet::Indented;
+        }
+        finished
+    }
+
+    pub fn finish(&mut self) -> Option<BuildDiagnostic> {
+        self.snippet = Snippet::Done;
+        self.pending.take()
+    }
+
+    // Adds the line to the pending diagnostic (or its last note) if it is part of it
+    fn continue_pending(&mut self, line: &str) -> bool {
+        let Some(pending) = self.pending.as_mut() else {
+            return false;
+        };
+        if self.progress.is_match(line)
+            || self.located.is_match(line)
+            || self.unlocated.is_match(line)
+            || line.trim().is_empty()
+        {
+            return false;
+        }
+
+        let target = match pending.notes.last_mut() {
+            Some(note) => note,
+            None => pending,
+        };
+        let framed = self.framed.is_match(line);
+        let indented =
+            line.starts_with(' ') || line.starts_with('\t') || line.starts_with(">>>");
+        let next = match self.snippet {
+            Snippet::Start if framed => Snippet::Framed,
+            Snippet::Start if line.trim_start().starts_with(">>>") => Snippet::Indented,
+            // Only diagnostics with a location are followed by the source line
+            Snippet::Start if target.line.is_some() => Snippet::Source,
+            Snippet::Source if self.caret.is_match(line) => Snippet::Caret,
+            Snippet::Caret if indented => {
+                target.fix_its.push(line.trim().to_string());
+                self.snippet = Snippet::Done;
+                return true;
+            }
+            Snippet::Framed if framed => Snippet::Framed,
+            Snippet::Indented if indented => Snippet::Indented,
+            _ => {
+                self.snippet = Snippet::Done;
+                return false;
+            }
+        };
+        target.snippet.push(line.to_string());
+        self.snippet = next;
+        true
+    }
+
+    fn parse_header(&mut self, line: &str) -> Option<BuildDiagnostic> {
+        if let Some(captures) = self.located.captures(line) {
+            // "<unknown>:0: error:" is used for diagnostics without a real location
+            let file = Some(&captures["file"])
+                .filter(|f| *f != "<unknown>")
+                .map(|file| self.normalize_path(file));
+            return Some(BuildDiagnostic {
+                severity: severity(&captures["severity"]),
+                message: captures["message"].to_string(),
+                file,
+                line: captures["line"].parse().ok().filter(|l| *l != 0),
+                column: captures.name("column").and_then(|c| c.as_str().parse().ok()),
+                snippet: Vec::new(),
+                fix_its: Vec::new(),
+                notes: Vec::new(),
+            });
+        }
+        self.unlocated.captures(line).map(|captures| BuildDiagnostic {
+            severity: severity(&captures["severity"]),
+            message: captures["message"].to_string(),
+            file: None,
+            line: None,
+            column: None,
+            snippet: Vec::new(),
+            fix_its: Vec::new(),
+            notes: Vec::new(),
+        })
+    }
+
+    // Converting a path may go through WSL, so each file is only converted once per build
+    fn normalize_path(&mut self, file: &str) -> String {
+        if !file.starts_with('/') {
+            return file.to_string();
+        }
+        self.paths
+            .entry(file.to_string())
+            .or_insert_with(|| windows_path(file))
+            .clone()
+    }
+}
+
+pub fn severity(value: &str) -> DiagnosticSeverity {
+    match value {
+        "error" => DiagnosticSeverity::Error,
+        "warning" => DiagnosticSeverity::Warning,
+        "note" => DiagnosticSeverity::Note,
+        _ => DiagnosticSeverity::Remark,
+    }
+}
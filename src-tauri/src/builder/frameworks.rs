@@ -0,0 +1,293 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use dircpy::CopyBuilder;
+
+use crate::builder::config::ProjectConfig;
+
+pub const FRAMEWORKS_RPATH: &str = "@executable_path/Frameworks";
+
+const CPU_TYPE_ARM64: u32 = 0x0100_000c;
+
+const LC_LOAD_DYLIB: u32 = 0xc;
+const LC_LOAD_WEAK_DYLIB: u32 = 0x8000_0018;
+const LC_REEXPORT_DYLIB: u32 = 0x8000_001f;
+const LC_LAZY_LOAD_DYLIB: u32 = 0x20;
+const LC_LOAD_UPWARD_DYLIB: u32 = 0x8000_0023;
+const LC_RPATH: u32 = 0x8000_001c;
+
+struct LoadCommands {
+    dylibs: Vec<String>,
+    rpaths: Vec<String>,
+}
+
+// Copies every @rpath framework and dylib the executable (transitively) links against into
+// <App>.app/Frameworks, looking in the build products and in the ios-arm64 slice of XCFrameworks.
+pub fn embed_frameworks(
+    exec: &Path,
+    app_path: &Path,
+    products_dir: &Path,
+    config: &ProjectConfig,
+) -> Result<(), String> {
+    let available = find_linkable_binaries(products_dir, config)?;
+    let frameworks_dir = app_path.join("Frameworks");
+
+    let mut embedded = HashSet::new();
+    let mut queue = VecDeque::from([exec.to_path_buf()]);
+    while let Some(binary) = queue.pop_front() {
+        let commands = read_load_commands(&binary)?;
+        for dylib in commands.dylibs {
+            let Some(name) = rpath_bundle_name(&dylib) else {
+                continue;
+            };
+            if embedded.contains(&name) {
+                continue;
+            }
+            let source = available.get(&name).ok_or(format!(
+                "Could not find {} (linked by {}) in the build products or any XCFramework",
+                dylib,
+                binary.display()
+            ))?;
+
+            fs::create_dir_all(&frameworks_dir)
+                .map_err(|e| format!("Failed to create Frameworks directory: {}", e))?;
+            let dest = frameworks_dir.join(&name);
+            if source.is_dir() {
+                CopyBuilder::new(source, &dest)
+                    .overwrite(true)
+                    .run()
+                    .map_err(|e| format!("Failed to copy {}: {}", source.display(), e))?;
+                let stem = Path::new(&name).file_stem().unwrap();
+                queue.push_back(dest.join(stem));
+            } else {
+                fs::copy(source, &dest)
+                    .map_err(|e| format!("Failed to copy {}: {}", source.display(), e))?;
+                queue.push_back(dest);
+            }
+            embedded.insert(name);
+        }
+    }
+
+    if !embedded.is_empty() {
+        let rpaths = read_load_commands(exec)?.rpaths;
+        if !rpaths
+            .iter()
+            .any(|r| r == FRAMEWORKS_RPATH || r == "@loader_path/Frameworks")
+        {
+            return Err(format!(
+                "Executable embeds frameworks but has no {} rpath",
+                FRAMEWORKS_RPATH
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// "@rpath/Foo.framework/Foo" -> "Foo.framework", "@rpath/libBar.dylib" -> "libBar.dylib".
+// The Swift runtime ships with iOS, so libswift* is never embedded.
+fn rpath_bundle_name(install_name: &str) -> Option<String> {
+    let rest = install_name.strip_prefix("@rpath/")?;
+    let first = rest.split('/').next()?;
+    if first.starts_with("libswift") {
+        return None;
+    }
+    Some(first.to_string())
+}
+
+fn find_linkable_binaries(
+    products_dir: &Path,
+    config: &ProjectConfig,
+) -> Result<HashMap<String, PathBuf>, String> {
+    let mut available = HashMap::new();
+
+    let mut xcframeworks: Vec<PathBuf> = config
+        .binary_targets
+        .iter()
+        .map(|p| config.project_path.join(p))
+        .filter(|p| p.extension().map_or(false, |ext| ext == "xcframework"))
+        .collect();
+    let artifacts = config.project_path.join(".build").join("artifacts");
+    if artifacts.exists() {
+        xcframeworks.extend(
+            walkdir::WalkDir::new(&artifacts)
+                .max_depth(3)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .map(|e| e.into_path())
+                .filter(|p| p.extension().map_or(false, |ext| ext == "xcframework")),
+        );
+    }
+    for xcframework in xcframeworks {
+        if let Some(slice) = ios_arm64_slice(&xcframework)? {
+            let name = slice.file_name().unwrap().to_string_lossy().to_string();
+            available.insert(name, slice);
+        }
+    }
+
+    // Build products win over prebuilt slices with the same name
+    let entries = fs::read_dir(products_dir)
+        .map_err(|e| format!("Failed to read build directory: {}", e))?;
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path
+            .extension()
+            .map_or(false, |ext| ext == "framework" || ext == "dylib")
+        {
+            available.insert(entry.file_name().to_string_lossy().to_string(), path);
+        }
+    }
+
+    Ok(available)
+}
+
+// Returns the framework or dylib for physical iOS devices, or None for static libraries
+fn ios_arm64_slice(xcframework: &Path) -> Result<Option<PathBuf>, String> {
+    let info = plist::Value::from_file(xcframework.join("Info.plist")).map_err(|e| {
+        format!(
+            "Failed to read Info.plist of {}: {}",
+            xcframework.display(),
+            e
+        )
+    })?;
+    let libraries = info
+        .as_dictionary()
+        .and_then(|d| d.get("AvailableLibraries"))
+        .and_then(|l| l.as_array())
+        .ok_or(format!(
+            "{} has no AvailableLibraries",
+            xcframework.display()
+        ))?;
+
+    let library = libraries.iter().filter_map(|l| l.as_dictionary()).find(|l| {
+        let string = |key: &str| l.get(key).and_then(|v| v.as_string());
+        let supports_arm64 = l
+            .get("SupportedArchitectures")
+            .and_then(|a| a.as_array())
+            .map_or(false, |a| a.iter().any(|v| v.as_string() == Some("arm64")));
+        string("SupportedPlatform") == Some("ios")
+            && string("SupportedPlatformVariant").is_none()
+            && supports_arm64
+    });
+
+    let slice = match library {
+        Some(library) => {
+            let identifier = library
+                .get("LibraryIdentifier")
+                .and_then(|v| v.as_string())
+                .unwrap_or("ios-arm64");
+            let path = library
+                .get("LibraryPath")
+                .and_then(|v| v.as_string())
+                .ok_or(format!(
+                    "{} slice {} has no LibraryPath",
+                    xcframework.display(),
+                    identifier
+                ))?;
+            xcframework.join(identifier).join(path)
+        }
+        None => return Ok(None),
+    };
+
+    if slice.extension().map_or(false, |ext| ext == "a") {
+        return Ok(None);
+    }
+    Ok(Some(slice))
+}
+
+fn read_load_commands(path: &Path) -> Result<LoadCommands, String> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let invalid = || format!("{} is not a valid Mach-O binary", path.display());
+
+    let be_u32 = |off: usize| -> Option<u32> {
+        Some(u32::from_be_bytes(data.get(off..off + 4)?.try_into().ok()?))
+    };
+    let be_u64 = |off: usize| -> Option<u64> {
+        Some(u64::from_be_bytes(data.get(off..off + 8)?.try_into().ok()?))
+    };
+
+    // Pick the arm64 slice out of fat binaries
+    let (start, end) = match be_u32(0).ok_or_else(invalid)? {
+        magic @ (0xcafe_babe | 0xcafe_babf) => {
+            let is_64 = magic == 0xcafe_babf;
+            let count = be_u32(4).ok_or_else(invalid)? as usize;
+            let entry_size = if is_64 { 32 } else { 20 };
+            let mut slice = None;
+            for i in 0..count {
+                let base = 8 + i * entry_size;
+                let cpu_type = be_u32(base).ok_or_else(invalid)?;
+                let (offset, size) = if is_64 {
+                    (be_u64(base + 8), be_u64(base + 16))
+                } else {
+                    (
+                        be_u32(base + 8).map(u64::from),
+                        be_u32(base + 12).map(u64::from),
+                    )
+                };
+                let (offset, size) = (
+                    offset.ok_or_else(invalid)? as usize,
+                    size.ok_or_else(invalid)? as usize,
+                );
+                if cpu_type == CPU_TYPE_ARM64 || slice.is_none() {
+                    slice = Some((offset, offset + size));
+                }
+                if cpu_type == CPU_TYPE_ARM64 {
+                    break;
+                }
+            }
+            slice.ok_or_else(invalid)?
+        }
+        _ => (0, data.len()),
+    };
+    let data = data.get(start..end).ok_or_else(invalid)?;
+
+    let le_u32 = |off: usize| -> Option<u32> {
+        Some(u32::from_le_bytes(data.get(off..off + 4)?.try_into().ok()?))
+    };
+    let header_size = match le_u32(0).ok_or_else(invalid)? {
+        0xfeed_facf => 32,
+        0xfeed_face => 28,
+        _ => return Err(invalid()),
+    };
+    let ncmds = le_u32(16).ok_or_else(invalid)?;
+    let c_string = |off: usize, end: usize| -> Option<String> {
+        let bytes = data.get(off..end)?;
+        let len = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+        Some(String::from_utf8_lossy(&bytes[..len]).to_string())
+    };
+
+    let mut commands = LoadCommands {
+        dylibs: Vec::new(),
+        rpaths: Vec::new(),
+    };
+    let mut offset = header_size;
+    for _ in 0..ncmds {
+        let cmd = le_u32(offset).ok_or_else(invalid)?;
+        let size = le_u32(offset + 4).ok_or_else(invalid)? as usize;
+        if size < 8 {
+            return Err(invalid());
+        }
+        match cmd {
+            LC_LOAD_DYLIB | LC_LOAD_WEAK_DYLIB | LC_REEXPORT_DYLIB | LC_LAZY_LOAD_DYLIB
+            | LC_LOAD_UPWARD_DYLIB => {
+                let name_offset = le_u32(offset + 8).ok_or_else(invalid)? as usize;
+                commands
+                    .dylibs
+                    .push(c_string(offset + name_offset, offset + size).ok_or_else(invalid)?);
+            }
+            LC_RPATH => {
+                let path_offset = le_u32(offset + 8).ok_or_else(invalid)? as usize;
+                commands
+                    .rpaths
+                    .push(c_string(offset + path_offset, offset + size).ok_or_else(invalid)?);
+            }
+            _ => {}
+        }
+        offset += size;
+    }
+
+    Ok(commands)
+}
@@ -0,0 +1,93 @@
+use std::{collections::BTreeMap, path::Path};
+
+use tauri::{Emitter, Window};
+
+use crate::{
+    builder::{
+        config::ProjectConfig,
+        crossplatform::{linux_path, linux_shell, set_linux_envs},
+        swift::pipe_command_as,
+    },
+    operation::Operation,
+};
+
+// [hooks] commands are run with bash (in WSL on Windows) from the project directory, with:
+//   YCODE_PROJECT_DIR    the project directory
+//   YCODE_PRODUCT        the executable product
+//   YCODE_BUNDLE_ID      the bundle id, including the profile's suffix
+//   YCODE_PROFILE        the selected build profile
+//   YCODE_CONFIGURATION  "debug" or "release"
+//   YCODE_APP_PATH       the packed .app, for post_pack and post_install
+// post_pack runs before the .ipa is zipped, so changes it makes to the .app end up in it.
+// Paths are Linux paths, under /mnt on Windows. A non-zero exit fails the operation.
+#[derive(Clone, Copy)]
+pub enum Hook {
+    PreBuild,
+    PostPack,
+    PostInstall,
+}
+
+impl Hook {
+    // Also the operation step id
+    pub fn name(&self) -> &'static str {
+        match self {
+            Hook::PreBuild => "pre_build",
+            Hook::PostPack => "post_pack",
+            Hook::PostInstall => "post_install",
+        }
+    }
+
+    fn command<'a>(&self, config: &'a ProjectConfig) -> Option<&'a str> {
+        match self {
+            Hook::PreBuild => config.hooks.pre_build.as_deref(),
+            Hook::PostPack => config.hooks.post_pack.as_deref(),
+            Hook::PostInstall => config.hooks.post_install.as_deref(),
+        }
+    }
+}
+
+fn hook_env(config: &ProjectConfig, app_path: Option<&Path>) -> BTreeMap<String, String> {
+    let mut env = BTreeMap::new();
+    let path = |p: &Path| linux_path(&p.to_string_lossy());
+    env.insert("YCODE_PROJECT_DIR".to_string(), path(&config.project_path));
+    env.insert("YCODE_PRODUCT".to_string(), config.product.clone());
+    env.insert("YCODE_BUNDLE_ID".to_string(), config.bundle_id.clone());
+    env.insert("YCODE_PROFILE".to_string(), config.build.profile.clone());
+    env.insert(
+        "YCODE_CONFIGURATION".to_string(),
+        config.build.configuration().to_string(),
+    );
+    if let Some(app_path) = app_path {
+        env.insert("YCODE_APP_PATH".to_string(), path(app_path));
+    }
+    env
+}
+
+// Runs the hook as its own operation step, which completes right away when it isn't configured
+pub async fn run_hook(
+    window: &Window,
+    op: &Operation,
+    config: &ProjectConfig,
+    hook: Hook,
+    app_path: Option<&Path>,
+) -> Result<(), String> {
+    let step = hook.name();
+    op.start(step)?;
+    let Some(script) = hook.command(config) else {
+        return op.complete(step);
+    };
+
+    window
+        .emit("build-output", format!("Running {} hook: {}", step, script))
+        .expect("failed to send output");
+    let mut cmd = linux_shell(script);
+    cmd.current_dir(&config.project_path);
+    set_linux_envs(&mut cmd, &hook_env(config, app_path));
+
+    let res = pipe_command_as(op.cancel_key(), &mut cmd, window, false, None).await;
+    op.fail_if_err(
+        step,
+        res.map_err(|e| format!("{} hook failed: {}", step, e)),
+    )?;
+    op.complete(step)
+}
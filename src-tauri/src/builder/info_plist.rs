@@ -0,0 +1,219 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::Path,
+};
+
+use plist::{Dictionary, Value};
+use regex::Regex;
+
+use crate::builder::config::ProjectConfig;
+
+// Generates the Info.plist for the app bundle from ycode.toml, deep-merges the project's own
+// Info.plist (if any) and the [info] table on top of it, then resolves [[key]] placeholders.
+pub fn build_info_plist(project_path: &Path, config: &ProjectConfig) -> Result<Value, String> {
+    let mut info = generate(project_path, config)?;
+
+    let user_plist = project_path.join("Info.plist");
+    if user_plist.exists() {
+        let overlay = Value::from_file(&user_plist)
+            .map_err(|e| format!("Failed to read Info.plist: {}", e))?
+            .into_dictionary()
+            .ok_or("Info.plist root is not a dictionary".to_string())?;
+        deep_merge(&mut info, overlay);
+    }
+    deep_merge(&mut info, config.info.clone());
+
+    let regex = Regex::new(r"\[\[([^\[\]]+)\]\]").map_err(|e| format!("Invalid regex: {}", e))?;
+    let mut info = Value::Dictionary(info);
+    resolve_placeholders(&mut info, &regex, &config.placeholders)?;
+    Ok(info)
+}
+
+// Info.plist written into a project when it is initialized. Values that come from ycode.toml are
+// left as placeholders so later changes to the config (and profiles) still apply. Device families,
+// orientations, the launch screen and icons are left out, since they are generated on every build
+// and a copy here would override later changes.
+pub fn template_info_plist() -> Value {
+    let mut info = fixed_keys();
+    for (key, placeholder) in [
+        ("CFBundleDisplayName", "display_name"),
+        ("CFBundleExecutable", "product"),
+        ("CFBundleIdentifier", "bundle_id"),
+        ("CFBundleName", "product"),
+        ("CFBundleShortVersionString", "version_string"),
+        ("CFBundleVersion", "version_num"),
+        ("MinimumOSVersion", "deployment_target"),
+    ] {
+        info.insert(key.into(), format!("[[{}]]", placeholder).into());
+    }
+    Value::Dictionary(info)
+}
+
+fn strings(values: &[&str]) -> Value {
+    Value::Array(values.iter().map(|v| Value::from(*v)).collect())
+}
+
+// Keys that depend on neither ycode.toml nor the project's resources
+fn fixed_keys() -> Dictionary {
+    let mut info = Dictionary::new();
+    info.insert("CFBundleDevelopmentRegion".into(), "en".into());
+    info.insert("CFBundleInfoDictionaryVersion".into(), "6.0".into());
+    info.insert("CFBundlePackageType".into(), "APPL".into());
+    info.insert("CFBundleSignature".into(), "????".into());
+    info.insert("CFBundleSupportedPlatforms".into(), strings(&["iPhoneOS"]));
+    info.insert("LSRequiresIPhoneOS".into(), true.into());
+    info.insert("UIRequiredDeviceCapabilities".into(), strings(&["arm64"]));
+    info
+}
+
+fn generate(project_path: &Path, config: &ProjectConfig) -> Result<Dictionary, String> {
+    let mut info = fixed_keys();
+    info.insert(
+        "CFBundleDisplayName".into(),
+        config.display_name.clone().into(),
+    );
+    info.insert("CFBundleExecutable".into(), config.product.clone().into());
+    info.insert("CFBundleIdentifier".into(), config.bundle_id.clone().into());
+    info.insert("CFBundleName".into(), config.product.clone().into());
+    info.insert(
+        "CFBundleShortVersionString".into(),
+        config.version_string.clone().into(),
+    );
+    info.insert("CFBundleVersion".into(), config.version_num.clone().into());
+    info.insert(
+        "MinimumOSVersion".into(),
+        config.deployment_target.clone().into(),
+    );
+    info.insert(
+        "UIDeviceFamily".into(),
+        Value::Array(config.device_family.iter().map(|f| (*f).into()).collect()),
+    );
+
+    let resources = project_path.join("Resources");
+    if resources.join("LaunchScreen.storyboardc").exists() {
+        info.insert("UILaunchStoryboardName".into(), "LaunchScreen".into());
+    } else {
+        info.insert("UILaunchScreen".into(), Value::Dictionary(Dictionary::new()));
+    }
+
+    let orientations = |values: &Vec<String>| {
+        Value::Array(values.iter().map(|o| o.clone().into()).collect())
+    };
+    info.insert(
+        "UISupportedInterfaceOrientations".into(),
+        orientations(&config.orientations),
+    );
+    if config.device_family.contains(&2) {
+        info.insert(
+            "UISupportedInterfaceOrientations~ipad".into(),
+            orientations(&config.ipad_orientations),
+        );
+    }
+
+    let icons = app_icons(&resources)?;
+    if !icons.is_empty() {
+        let mut primary = Dictionary::new();
+        primary.insert(
+            "CFBundleIconFiles".into(),
+            Value::Array(icons.into_iter().map(Value::from).collect()),
+        );
+        primary.insert("UIPrerenderedIcon".into(), true.into());
+        let mut bundle_icons = Dictionary::new();
+        bundle_icons.insert("CFBundlePrimaryIcon".into(), Value::Dictionary(primary));
+        info.insert("CFBundleIcons".into(), Value::Dictionary(bundle_icons.clone()));
+        if config.device_family.contains(&2) {
+            info.insert("CFBundleIcons~ipad".into(), Value::Dictionary(bundle_icons));
+        }
+    }
+
+    Ok(info)
+}
+
+// Icon base names (e.g. AppIcon60x60) for every AppIcon*.png in Resources
+fn app_icons(resources: &Path) -> Result<BTreeSet<String>, String> {
+    let mut icons = BTreeSet::new();
+    if !resources.exists() {
+        return Ok(icons);
+    }
+    let regex = Regex::new(r"^(AppIcon[0-9.]+x[0-9.]+)(@[23]x)?(~ipad)?\.png$")
+        .map_err(|e| format!("Invalid regex: {}", e))?;
+    let entries =
+        fs::read_dir(resources).map_err(|e| format!("Failed to read Resources: {}", e))?;
+    for entry in entries.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if let Some(captures) = regex.captures(&name) {
+            icons.insert(captures[1].to_string());
+        }
+    }
+    Ok(icons)
+}
+
+fn resolve_placeholders(
+    value: &mut Value,
+    regex: &Regex,
+    placeholders: &HashMap<String, String>,
+) -> Result<(), String> {
+    match value {
+        Value::String(s) => {
+            if let Some(missing) = regex
+                .captures_iter(s)
+                .map(|c| c[1].to_string())
+                .find(|key| !placeholders.contains_key(key))
+            {
+                return Err(format!(
+                    "Unresolved placeholder [[{}]] in Info.plist value \"{}\"",
+                    missing, s
+                ));
+            }
+            *s = regex
+                .replace_all(s, |c: &regex::Captures| placeholders[&c[1]].clone())
+                .to_string();
+        }
+        Value::Array(values) => {
+            for value in values {
+                resolve_placeholders(value, regex, placeholders)?;
+            }
+        }
+        Value::Dictionary(dict) => {
+            for value in dict.values_mut() {
+                resolve_placeholders(value, regex, placeholders)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+pub fn toml_to_plist(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::Integer(i.into()),
+        toml::Value::Float(f) => Value::Real(f),
+        toml::Value::Boolean(b) => Value::Boolean(b),
+        toml::Value::Datetime(d) => plist::Date::from_xml_format(&d.to_string())
+            .map(Value::Date)
+            .unwrap_or_else(|_| Value::String(d.to_string())),
+        toml::Value::Array(values) => Value::Array(values.into_iter().map(toml_to_plist).collect()),
+        toml::Value::Table(table) => Value::Dictionary(
+            table
+                .into_iter()
+                .map(|(k, v)| (k, toml_to_plist(v)))
+                .collect(),
+        ),
+    }
+}
+
+// Dictionaries are merged recursively, everything else in the overlay replaces the base value
+pub fn deep_merge(base: &mut Dictionary, overlay: Dictionary) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Dictionary(existing)), Value::Dictionary(incoming)) => {
+                deep_merge(existing, incoming)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
@@ -0,0 +1,414 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::builder::swift::SwiftBin;
+
+// Typed model of `swift package dump-package`. The dump's raw shape (single element arrays,
+// `_0` keys) is flattened into something the frontend can show as a tree.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageModel {
+    pub name: String,
+    pub tools_version: Option<String>,
+    pub platforms: Vec<Platform>,
+    pub products: Vec<Product>,
+    pub dependencies: Vec<PackageDependency>,
+    pub targets: Vec<Target>,
+    pub swift_language_versions: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Platform {
+    pub platform_name: String,
+    pub version: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Product {
+    pub name: String,
+    pub product_type: ProductType,
+    pub targets: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ProductType {
+    Executable,
+    // "automatic", "static" or "dynamic"
+    Library(Vec<String>),
+    Plugin,
+    Snippet,
+    Test,
+    Macro,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageDependency {
+    pub identity: String,
+    // "sourceControl", "fileSystem" or "registry"
+    pub kind: String,
+    // URL or path
+    pub location: Option<String>,
+    // e.g. "1.0.0..<2.0.0", "exact 1.2.3", "branch main"
+    pub requirement: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Target {
+    pub name: String,
+    // "regular", "executable", "test", "binary", "plugin", "macro", "system"
+    pub target_type: String,
+    pub path: Option<String>,
+    pub sources: Option<Vec<String>>,
+    pub resources: Vec<Resource>,
+    pub exclude: Vec<String>,
+    pub dependencies: Vec<TargetDependency>,
+    pub settings: Vec<TargetSetting>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Resource {
+    pub path: String,
+    // "process", "copy", "embedInCode"
+    pub rule: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetDependency {
+    // "byName", "target" or "product"
+    pub kind: String,
+    pub name: String,
+    pub package: Option<String>,
+    pub platforms: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetSetting {
+    // "swift", "c", "cxx" or "linker"
+    pub tool: String,
+    // e.g. "define", "unsafeFlags", "linkedFramework", "enableUpcomingFeature"
+    pub kind: String,
+    pub values: Vec<String>,
+    pub platforms: Vec<String>,
+    pub configuration: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedModel {
+    key: String,
+    model: PackageModel,
+}
+
+impl PackageModel {
+    pub fn executable_products(&self) -> Vec<String> {
+        let declared = self
+            .products
+            .iter()
+            .filter(|p| p.product_type == ProductType::Executable);
+        let mut products: Vec<String> = declared.clone().map(|p| p.name.clone()).collect();
+        // SwiftPM synthesizes a product for executable targets that no executable product exports
+        for target in self
+            .targets
+            .iter()
+            .filter(|t| t.target_type == "executable")
+        {
+            if !declared.clone().any(|p| p.targets.contains(&target.name))
+                && !products.contains(&target.name)
+            {
+                products.push(target.name.clone());
+            }
+        }
+        products
+    }
+
+    // The package's own targets built for `product`, following target dependencies. Test targets
+    // and targets only other products use are left out.
+    pub fn product_targets(&self, product: &str) -> Vec<&Target> {
+        let mut pending: Vec<String> = match self.products.iter().find(|p| p.name == product) {
+            Some(product) => product.targets.clone(),
+            None => vec![product.to_string()],
+        };
+        let mut targets: Vec<&Target> = Vec::new();
+        while let Some(name) = pending.pop() {
+            if targets.iter().any(|t| t.name == name) {
+                continue;
+            }
+            let Some(target) = self.targets.iter().find(|t| t.name == name) else {
+                continue;
+            };
+            if target.target_type == "test" {
+                continue;
+            }
+            pending.extend(
+                target
+                    .dependencies
+                    .iter()
+                    .filter(|d| d.kind != "product")
+                    .map(|d| d.name.clone()),
+            );
+            targets.push(target);
+        }
+        targets
+    }
+
+    fn from_dump(dump: &Value) -> Result<Self, String> {
+        let name = string(&dump["name"]).ok_or("Package dump has no name".to_string())?;
+        Ok(PackageModel {
+            name,
+            tools_version: string(&dump["toolsVersion"]["_version"]),
+            platforms: array(&dump["platforms"])
+                .iter()
+                .filter_map(|p| {
+                    Some(Platform {
+                        platform_name: string(&p["platformName"])?,
+                        version: string(&p["version"])?,
+                    })
+                })
+                .collect(),
+            products: array(&dump["products"])
+                .iter()
+                .filter_map(|p| {
+                    Some(Product {
+                        name: string(&p["name"])?,
+                        product_type: serde_json::from_value(p["type"].clone()).ok()?,
+                        targets: strings(&p["targets"]),
+                    })
+                })
+                .collect(),
+            dependencies: array(&dump["dependencies"])
+                .iter()
+                .filter_map(package_dependency)
+                .collect(),
+            targets: array(&dump["targets"])
+                .iter()
+                .filter_map(|t| {
+                    Some(Target {
+                        name: string(&t["name"])?,
+                        target_type: string(&t["type"])?,
+                        path: string(&t["path"]),
+                        sources: t["sources"].as_array().map(|_| strings(&t["sources"])),
+                        resources: array(&t["resources"])
+                            .iter()
+                            .filter_map(|r| {
+                                Some(Resource {
+                                    path: string(&r["path"])?,
+                                    rule: first_key(&r["rule"])?,
+                                })
+                            })
+                            .collect(),
+                        exclude: strings(&t["exclude"]),
+                        dependencies: array(&t["dependencies"])
+                            .iter()
+                            .filter_map(target_dependency)
+                            .collect(),
+                        settings: array(&t["settings"])
+                            .iter()
+                            .filter_map(target_setting)
+                            .collect(),
+                    })
+                })
+                .collect(),
+            swift_language_versions: dump["swiftLanguageVersions"]
+                .as_array()
+                .map(|versions| versions.iter().filter_map(language_version).collect()),
+        })
+    }
+}
+
+fn string(value: &Value) -> Option<String> {
+    value.as_str().map(String::from)
+}
+
+fn array(value: &Value) -> &[Value] {
+    value.as_array().map_or(&[], |a| a.as_slice())
+}
+
+fn strings(value: &Value) -> Vec<String> {
+    array(value).iter().filter_map(string).collect()
+}
+
+// Enums are dumped as { "case": payload }
+fn first_key(value: &Value) -> Option<String> {
+    value.as_object()?.keys().next().cloned()
+}
+
+// { "sourceControl": [{ "identity": ..., "location": { "remote": [{ "urlString": ... }] }, ... }] }
+fn package_dependency(value: &Value) -> Option<PackageDependency> {
+    let kind = first_key(value)?;
+    let details = &value[&kind][0];
+    let location = match &details["location"] {
+        Value::String(location) => Some(location.clone()),
+        Value::Object(location) => location.values().next().and_then(|l| {
+            let l = &l[0];
+            string(l).or_else(|| string(&l["urlString"]))
+        }),
+        _ => string(&details["path"]),
+    };
+    let requirement = details["requirement"].as_object().and_then(|requirement| {
+        let (kind, value) = requirement.iter().next()?;
+        let value = &value[0];
+        Some(match kind.as_str() {
+            "range" => format!(
+                "{}..<{}",
+                string(&value["lowerBound"])?,
+                string(&value["upperBound"])?
+            ),
+            _ => format!("{} {}", kind, string(value)?),
+        })
+    });
+    Some(PackageDependency {
+        identity: string(&details["identity"])?,
+        kind,
+        location,
+        requirement,
+    })
+}
+
+// { "product": ["Name", "package", moduleAliases, condition] }, { "target": ["Name", condition] }
+fn target_dependency(value: &Value) -> Option<TargetDependency> {
+    let kind = first_key(value)?;
+    let fields = array(&value[&kind]);
+    let (package, condition) = match kind.as_str() {
+        "product" => (fields.get(1).and_then(string), fields.get(3)),
+        _ => (None, fields.get(1)),
+    };
+    Some(TargetDependency {
+        name: string(fields.first()?)?,
+        kind,
+        package,
+        platforms: condition_platforms(condition.unwrap_or(&Value::Null)),
+    })
+}
+
+// { "tool": "swift", "kind": { "define": { "_0": "DEBUG" } }, "condition": { ... } }
+fn target_setting(value: &Value) -> Option<TargetSetting> {
+    let kind = first_key(&value["kind"])?;
+    let payload = &value["kind"][&kind]["_0"];
+    let values = match payload {
+        Value::Array(_) => strings(payload),
+        Value::Null => Vec::new(),
+        other => vec![string(other).unwrap_or_else(|| other.to_string())],
+    };
+    Some(TargetSetting {
+        tool: string(&value["tool"])?,
+        kind,
+        values,
+        platforms: condition_platforms(&value["condition"]),
+        configuration: string(&value["condition"]["config"]),
+    })
+}
+
+fn condition_platforms(condition: &Value) -> Vec<String> {
+    array(&condition["platformNames"])
+        .iter()
+        .filter_map(string)
+        .collect()
+}
+
+// Plain strings in older dumps, { "version": ["5"] } in newer ones
+fn language_version(value: &Value) -> Option<String> {
+    string(value).or_else(|| {
+        let key = first_key(value)?;
+        string(&value[&key][0]).or(Some(key))
+    })
+}
+
+// Manifests, including version specific ones like Package@swift-5.9.swift, and the toolchain
+fn cache_key(project_path: &Path, toolchain_path: &str) -> Result<String, String> {
+    let mut manifests: Vec<PathBuf> = std::fs::read_dir(project_path)
+        .map_err(|e| format!("Failed to read project directory: {}", e))?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n == "Package.swift" || n.starts_with("Package@swift-"))
+        })
+        .collect();
+    manifests.sort();
+
+    let mut hasher = Sha256::new();
+    hasher.update(toolchain_path.as_bytes());
+    for manifest in manifests {
+        let content = std::fs::read(&manifest)
+            .map_err(|e| format!("Failed to read {}: {}", manifest.display(), e))?;
+        hasher.update(
+            manifest
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .as_bytes(),
+        );
+        hasher.update(&content);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn cache_path(project_path: &Path) -> PathBuf {
+    project_path.join(".ycode").join("package-model.json")
+}
+
+// Returns the package model, only running dump-package when a manifest or the toolchain changed
+pub fn load_package_model(
+    project_path: &Path,
+    toolchain_path: &str,
+) -> Result<PackageModel, String> {
+    let key = cache_key(project_path, toolchain_path)?;
+    let cached = std::fs::read_to_string(cache_path(project_path))
+        .ok()
+        .and_then(|content| serde_json::from_str::<CachedModel>(&content).ok());
+    if let Some(cached) = cached.filter(|c| c.key == key) {
+        return Ok(cached.model);
+    }
+
+    let model = dump_package(project_path, toolchain_path)?;
+    // The cache is only an optimization, failing to write it is not an error
+    if std::fs::create_dir_all(project_path.join(".ycode")).is_ok() {
+        if let Ok(content) = serde_json::to_string(&CachedModel {
+            key,
+            model: model.clone(),
+        }) {
+            std::fs::write(cache_path(project_path), content).ok();
+        }
+    }
+    Ok(model)
+}
+
+fn dump_package(project_path: &Path, toolchain_path: &str) -> Result<PackageModel, String> {
+    let swift = SwiftBin::new(toolchain_path)?;
+    let raw_package = swift
+        .command()
+        .arg("package")
+        .arg("dump-package")
+        .current_dir(project_path)
+        .output()
+        .map_err(|e| format!("Failed to execute swift command: {}", e))?;
+    if !raw_package.status.success() {
+        return Err(format!(
+            "Failed to dump package: {}",
+            String::from_utf8_lossy(&raw_package.stderr)
+        ));
+    }
+
+    let dump: Value = serde_json::from_slice(&raw_package.stdout)
+        .map_err(|e| format!("Failed to parse package dump: {}", e))?;
+    PackageModel::from_dump(&dump)
+}
+
+#[tauri::command]
+pub async fn get_package_model(
+    folder: String,
+    toolchain_path: String,
+) -> Result<PackageModel, String> {
+    load_package_model(Path::new(&folder), &toolchain_path)
+}
@@ -0,0 +1,669 @@
+// Minimal Package.swift rewriter for toolchains without `swift package add-dependency` and for
+// removals, which SwiftPM has no command for. It only understands the shape of the calls it edits
+// (brackets, labels and string literals), anything it misses is caught by dump-package afterwards.
+
+#[derive(Clone, Debug, PartialEq)]
+enum Kind {
+    Open(char),
+    Close(char),
+    Comma,
+    Colon,
+    Dot,
+    Ident,
+    Str,
+    Other,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    kind: Kind,
+    start: usize,
+    end: usize,
+    // Identifier name or string literal contents
+    text: String,
+}
+
+struct Manifest<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    // Index of the matching bracket for every bracket token
+    matching: Vec<Option<usize>>,
+}
+
+// Token ranges of the top-level arguments or elements between a pair of brackets
+type Args = Vec<(usize, usize)>;
+
+impl<'a> Manifest<'a> {
+    fn parse(source: &'a str) -> Result<Self, String> {
+        let tokens = tokenize(source)?;
+        let mut matching = vec![None; tokens.len()];
+        let mut stack: Vec<usize> = Vec::new();
+        for (i, token) in tokens.iter().enumerate() {
+            match token.kind {
+                Kind::Open(_) => stack.push(i),
+                Kind::Close(close) => {
+                    let open = stack
+                        .pop()
+                        .ok_or("Unbalanced brackets in Package.swift".to_string())?;
+                    if tokens[open].kind != Kind::Open(opening(close)) {
+                        return Err("Unbalanced brackets in Package.swift".to_string());
+                    }
+                    matching[open] = Some(i);
+                    matching[i] = Some(open);
+                }
+                _ => {}
+            }
+        }
+        if !stack.is_empty() {
+            return Err("Unbalanced brackets in Package.swift".to_string());
+        }
+        Ok(Manifest {
+            source,
+            tokens,
+            matching,
+        })
+    }
+
+    fn args(&self, open: usize) -> Args {
+        let close = self.matching[open].unwrap_or(open);
+        let mut args = Vec::new();
+        let mut start = open + 1;
+        let mut i = open + 1;
+        while i < close {
+            match self.tokens[i].kind {
+                Kind::Open(_) => i = self.matching[i].unwrap_or(i),
+                Kind::Comma => {
+                    if start < i {
+                        args.push((start, i));
+                    }
+                    start = i + 1;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        if start < close {
+            args.push((start, close));
+        }
+        args
+    }
+
+    fn label(&self, arg: (usize, usize)) -> Option<&str> {
+        match (self.tokens.get(arg.0), self.tokens.get(arg.0 + 1)) {
+            (Some(ident), Some(colon))
+                if ident.kind == Kind::Ident && colon.kind == Kind::Colon =>
+            {
+                Some(&ident.text)
+            }
+            _ => None,
+        }
+    }
+
+    // The opening bracket of the argument's value, e.g. the `[` of `dependencies: [...]`
+    fn value_bracket(&self, arg: (usize, usize)) -> Option<usize> {
+        let value = if self.label(arg).is_some() {
+            arg.0 + 2
+        } else {
+            arg.0
+        };
+        match self.tokens.get(value)?.kind {
+            Kind::Open(_) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn labeled(&self, args: &Args, label: &str) -> Option<(usize, usize)> {
+        args.iter()
+            .copied()
+            .find(|arg| self.label(*arg) == Some(label))
+    }
+
+    // String value of `label: "..."`
+    fn string_arg(&self, args: &Args, label: &str) -> Option<&str> {
+        let arg = self.labeled(args, label)?;
+        let value = self.tokens.get(arg.0 + 2)?;
+        (value.kind == Kind::Str).then_some(value.text.as_str())
+    }
+
+    // `.name(` at the start of an element, returns the index of the `(`
+    fn member_call(&self, arg: (usize, usize), names: &[&str]) -> Option<usize> {
+        let t = &self.tokens;
+        let ok = arg.1 - arg.0 >= 3
+            && t[arg.0].kind == Kind::Dot
+            && t[arg.0 + 1].kind == Kind::Ident
+            && names.contains(&t[arg.0 + 1].text.as_str())
+            && t[arg.0 + 2].kind == Kind::Open('(');
+        ok.then_some(arg.0 + 2)
+    }
+
+    // The argument list of `Package(...)`
+    fn package_call(&self) -> Result<usize, String> {
+        self.tokens
+            .windows(2)
+            .position(|w| {
+                w[0].kind == Kind::Ident && w[0].text == "Package" && w[1].kind == Kind::Open('(')
+            })
+            .map(|i| i + 1)
+            .ok_or("Package.swift has no Package(...) declaration".to_string())
+    }
+
+    fn target_call(&self, name: &str) -> Result<usize, String> {
+        let package = self.args(self.package_call()?);
+        let targets = self
+            .labeled(&package, "targets")
+            .and_then(|arg| self.value_bracket(arg))
+            .ok_or("Package.swift has no targets".to_string())?;
+        for element in self.args(targets) {
+            let Some(call) = self.member_call(
+                element,
+                &[
+                    "target",
+                    "executableTarget",
+                    "testTarget",
+                    "macro",
+                    "plugin",
+                ],
+            ) else {
+                continue;
+            };
+            if self.string_arg(&self.args(call), "name") == Some(name) {
+                return Ok(call);
+            }
+        }
+        Err(format!("Target {} not found in Package.swift", name))
+    }
+
+    fn line_start(&self, pos: usize) -> usize {
+        self.source[..pos].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    fn indent(&self, pos: usize) -> &str {
+        let start = self.line_start(pos);
+        let line = &self.source[start..];
+        &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+    }
+
+    fn same_line(&self, a: usize, b: usize) -> bool {
+        !self.source[a.min(b)..a.max(b)].contains('\n')
+    }
+
+    // Appends an element to the array starting at `open`
+    fn append_element(&self, open: usize, element: &str) -> (usize, usize, String) {
+        let close = self.matching[open].unwrap_or(open);
+        let elements = self.args(open);
+        let open_end = self.tokens[open].end;
+        let close_start = self.tokens[close].start;
+        let Some(last) = elements.last() else {
+            let indent = self.indent(self.tokens[open].start);
+            return (
+                open_end,
+                close_start,
+                format!("\n{}    {},\n{}", indent, element, indent),
+            );
+        };
+        let first_start = self.tokens[elements[0].0].start;
+        let last_end = self.tokens[last.1 - 1].end;
+        let trailing_comma = self.tokens[last.1].kind == Kind::Comma;
+        if self.same_line(open_end, first_start) {
+            return if trailing_comma {
+                (
+                    self.tokens[last.1].end,
+                    self.tokens[last.1].end,
+                    format!(" {},", element),
+                )
+            } else {
+                (last_end, last_end, format!(", {}", element))
+            };
+        }
+        let indent = self.indent(first_start);
+        if trailing_comma {
+            let at = self.tokens[last.1].end;
+            (at, at, format!("\n{}{},", indent, element))
+        } else {
+            (last_end, last_end, format!(",\n{}{}", indent, element))
+        }
+    }
+
+    // Adds `label: [element]` to a call, after the last argument labeled with one of `after`
+    fn insert_array_arg(
+        &self,
+        call: usize,
+        after: &[&str],
+        label: &str,
+        element: &str,
+    ) -> (usize, usize, String) {
+        let args = self.args(call);
+        let position = args
+            .iter()
+            .rposition(|arg| self.label(*arg).is_some_and(|l| after.contains(&l)));
+        let next = match position {
+            Some(i) => args.get(i + 1).copied(),
+            None => args.first().copied(),
+        };
+        if let Some(next) = next {
+            let at = self.tokens[next.0].start;
+            let previous_end = self.tokens[next.0 - 1].end;
+            if self.same_line(previous_end, at) {
+                return (at, at, format!("{}: [{}], ", label, element));
+            }
+            let indent = self.indent(at);
+            return (
+                at,
+                at,
+                format!(
+                    "{}: [\n{}    {},\n{}],\n{}",
+                    label, indent, element, indent, indent
+                ),
+            );
+        }
+        // No argument follows, append to the end of the call
+        let close = self.matching[call].unwrap_or(call);
+        let Some(last) = args.last() else {
+            let at = self.tokens[call].end;
+            return (at, at, format!("{}: [{}]", label, element));
+        };
+        let last_end = self.tokens[last.1 - 1].end;
+        if self.same_line(last_end, self.tokens[close].start) {
+            return (last_end, last_end, format!(", {}: [{}]", label, element));
+        }
+        let indent = self.indent(self.tokens[last.0].start);
+        let (at, prefix, suffix) = if self.tokens[last.1].kind == Kind::Comma {
+            (self.tokens[last.1].end, "", ",")
+        } else {
+            (last_end, ",", "")
+        };
+        (
+            at,
+            at,
+            format!(
+                "{}\n{}{}: [\n{}    {},\n{}]{}",
+                prefix, indent, label, indent, element, indent, suffix
+            ),
+        )
+    }
+
+    // Byte range that removes an element together with its comma and, if it is on its own
+    // line, the whole line
+    fn element_removal(&self, open: usize, index: usize) -> (usize, usize) {
+        let elements = self.args(open);
+        let element = elements[index];
+        if elements.len() == 1 {
+            let close = self.matching[open].unwrap_or(open);
+            return (self.tokens[open].end, self.tokens[close].start);
+        }
+        let start = self.tokens[element.0].start;
+        let mut end = self.tokens[element.1 - 1].end;
+        if self.tokens[element.1].kind == Kind::Comma {
+            end = self.tokens[element.1].end;
+        } else {
+            // Last element without a trailing comma, remove the comma before it instead
+            return (self.tokens[elements[index - 1].1 - 1].end, end);
+        }
+
+        let line_start = self.line_start(start);
+        let line_end = self.source[end..]
+            .find('\n')
+            .map_or(self.source.len(), |i| end + i + 1);
+        if self.source[line_start..start].trim().is_empty()
+            && self.source[end..line_end].trim().is_empty()
+        {
+            return (line_start, line_end);
+        }
+        let rest = &self.source[end..];
+        end += rest.len() - rest.trim_start_matches(' ').len();
+        (start, end)
+    }
+
+    fn package_dependencies(&self) -> Result<Option<usize>, String> {
+        let package = self.args(self.package_call()?);
+        Ok(self
+            .labeled(&package, "dependencies")
+            .and_then(|arg| self.value_bracket(arg)))
+    }
+
+    // Index of the `.package(...)` element for `identity` in the package dependencies
+    fn find_dependency(&self, open: usize, identity: &str) -> Option<usize> {
+        self.args(open).into_iter().position(|element| {
+            let Some(call) = self.member_call(element, &["package"]) else {
+                return false;
+            };
+            let args = self.args(call);
+            ["url", "path", "id"].iter().any(|label| {
+                self.string_arg(&args, label)
+                    .is_some_and(|location| identity_from_location(location) == identity)
+            }) || self
+                .string_arg(&args, "name")
+                .is_some_and(|name| name.to_lowercase() == identity)
+        })
+    }
+}
+
+fn opening(close: char) -> char {
+    match close {
+        ')' => '(',
+        ']' => '[',
+        _ => '{',
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let token = |kind: Kind, start: usize, end: usize, text: String| Token {
+        kind,
+        start,
+        end,
+        text,
+    };
+    while i < bytes.len() {
+        let c = bytes[i];
+        match c {
+            b' ' | b'\t' | b'\r' | b'\n' => i += 1,
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i = source[i..].find('\n').map_or(bytes.len(), |n| i + n);
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                // Block comments nest in Swift
+                let mut depth = 0;
+                while i < bytes.len() {
+                    if bytes[i..].starts_with(b"/*") {
+                        depth += 1;
+                        i += 2;
+                    } else if bytes[i..].starts_with(b"*/") {
+                        depth -= 1;
+                        i += 2;
+                        if depth == 0 {
+                            break;
+                        }
+                    } else {
+                        i += 1;
+                    }
+                }
+            }
+            b'"' | b'#' => {
+                let start = i;
+                let hashes = bytes[i..].iter().take_while(|b| **b == b'#').count();
+                i += hashes;
+                if bytes.get(i) != Some(&b'"') {
+                    tokens.push(token(Kind::Other, start, i, String::new()));
+                    continue;
+                }
+                let delimiter = if bytes[i..].starts_with(b"\"\"\"") {
+                    "\"\"\""
+                } else {
+                    "\""
+                };
+                i += delimiter.len();
+                // Compared as bytes, `i` may point into a multi-byte character
+                let closing = format!("{}{}", delimiter, "#".repeat(hashes));
+                let content_start = i;
+                loop {
+                    if i >= bytes.len() {
+                        return Err("Unterminated string in Package.swift".to_string());
+                    }
+                    if hashes == 0 && bytes[i] == b'\\' {
+                        i += 2;
+                    } else if bytes[i..].starts_with(closing.as_bytes()) {
+                        break;
+                    } else {
+                        i += 1;
+                    }
+                }
+                let text = source[content_start..i].to_string();
+                i += closing.len();
+                tokens.push(token(Kind::Str, start, i, text));
+            }
+            b'(' | b'[' | b'{' => {
+                tokens.push(token(Kind::Open(c as char), i, i + 1, String::new()));
+                i += 1;
+            }
+            b')' | b']' | b'}' => {
+                tokens.push(token(Kind::Close(c as char), i, i + 1, String::new()));
+                i += 1;
+            }
+            b',' | b':' | b'.' => {
+                let kind = match c {
+                    b',' => Kind::Comma,
+                    b':' => Kind::Colon,
+                    _ => Kind::Dot,
+                };
+                tokens.push(token(kind, i, i + 1, String::new()));
+                i += 1;
+            }
+            c if c.is_ascii_alphabetic() || c == b'_' => {
+                let start = i;
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                tokens.push(token(Kind::Ident, start, i, source[start..i].to_string()));
+            }
+            _ => {
+                let start = i;
+                i += source[i..].chars().next().map_or(1, |c| c.len_utf8());
+                tokens.push(token(Kind::Other, start, i, String::new()));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn apply(source: &str, (start, end, text): (usize, usize, String)) -> String {
+    format!("{}{}{}", &source[..start], text, &source[end..])
+}
+
+// SwiftPM's identity: the last path component of the URL or path, lowercased, without .git
+pub fn identity_from_location(location: &str) -> String {
+    location
+        .trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()
+        .unwrap_or(location)
+        .trim_end_matches(".git")
+        .to_lowercase()
+}
+
+pub fn swift_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+pub fn has_dependency(source: &str, identity: &str) -> Result<bool, String> {
+    let manifest = Manifest::parse(source)?;
+    Ok(match manifest.package_dependencies()? {
+        Some(open) => manifest.find_dependency(open, identity).is_some(),
+        None => false,
+    })
+}
+
+// Adds a `.package(...)` expression to the package dependencies
+pub fn add_dependency(source: &str, dependency: &str) -> Result<String, String> {
+    let manifest = Manifest::parse(source)?;
+    let edit = match manifest.package_dependencies()? {
+        Some(open) => manifest.append_element(open, dependency),
+        None => manifest.insert_array_arg(
+            manifest.package_call()?,
+            &[
+                "name",
+                "defaultLocalization",
+                "platforms",
+                "pkgConfig",
+                "providers",
+                "products",
+            ],
+            "dependencies",
+            dependency,
+        ),
+    };
+    Ok(apply(source, edit))
+}
+
+// Adds a `.product(...)` expression to a target's dependencies
+pub fn add_target_dependency(source: &str, target: &str, product: &str) -> Result<String, String> {
+    let manifest = Manifest::parse(source)?;
+    let call = manifest.target_call(target)?;
+    let args = manifest.args(call);
+    let edit = match manifest
+        .labeled(&args, "dependencies")
+        .and_then(|arg| manifest.value_bracket(arg))
+    {
+        Some(open) => manifest.append_element(open, product),
+        None => manifest.insert_array_arg(call, &["name"], "dependencies", product),
+    };
+    Ok(apply(source, edit))
+}
+
+// Removes the package and every `.product(..., package:)` of it from the targets
+pub fn remove_dependency(source: &str, identity: &str) -> Result<String, String> {
+    let mut source = source.to_string();
+    let manifest = Manifest::parse(&source)?;
+    let open = manifest
+        .package_dependencies()?
+        .ok_or("Package.swift has no dependencies".to_string())?;
+    let index = manifest
+        .find_dependency(open, identity)
+        .ok_or(format!("{} is not a dependency of this package", identity))?;
+    let (start, end) = manifest.element_removal(open, index);
+    source = apply(&source, (start, end, String::new()));
+
+    // Remove product dependencies one at a time, every removal shifts the offsets
+    loop {
+        let manifest = Manifest::parse(&source)?;
+        let package = manifest.args(manifest.package_call()?);
+        let Some(targets) = manifest
+            .labeled(&package, "targets")
+            .and_then(|arg| manifest.value_bracket(arg))
+        else {
+            break;
+        };
+        let mut removal = None;
+        'targets: for target in manifest.args(targets) {
+            let Some(call) = manifest.member_call(
+                target,
+                &[
+                    "target",
+                    "executableTarget",
+                    "testTarget",
+                    "macro",
+                    "plugin",
+                ],
+            ) else {
+                continue;
+            };
+            let args = manifest.args(call);
+            let Some(open) = manifest
+                .labeled(&args, "dependencies")
+                .and_then(|arg| manifest.value_bracket(arg))
+            else {
+                continue;
+            };
+            for (index, element) in manifest.args(open).into_iter().enumerate() {
+                let Some(product) = manifest.member_call(element, &["product"]) else {
+                    continue;
+                };
+                let product_args = manifest.args(product);
+                if manifest
+                    .string_arg(&product_args, "package")
+                    .is_some_and(|package| package.to_lowercase() == identity)
+                {
+                    removal = Some(manifest.element_removal(open, index));
+                    break 'targets;
+                }
+            }
+        }
+        match removal {
+            Some((start, end)) => source = apply(&source, (start, end, String::new())),
+            None => break,
+        }
+    }
+    Ok(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(source: &str) -> Vec<String> {
+        tokenize(source)
+            .unwrap()
+            .into_iter()
+            .filter(|t| t.kind == Kind::Str)
+            .map(|t| t.text)
+            .collect()
+    }
+
+    const MANIFEST: &str = r#"// swift-tools-version:5.9
+/* Café — notes /* nested ünïcode */ still a comment */
+import PackageDescription
+
+let package = Package(
+    name: "Café",
+    dependencies: [
+        .package(url: "https://github.com/apple/swift-log.git", from: "1.0.0"),
+    ],
+    targets: [
+        .executableTarget(name: "Café", dependencies: [])
+    ]
+)
+"#;
+
+    #[test]
+    fn skips_non_ascii_block_comments() {
+        let tokens = tokenize("/* Café — notes */ let x /* ✓ /* ✓ */ ✓ */ = 1").unwrap();
+        let idents: Vec<&str> = tokens
+            .iter()
+            .filter(|t| t.kind == Kind::Ident)
+            .map(|t| t.text.as_str())
+            .collect();
+        assert_eq!(idents, ["let", "x"]);
+    }
+
+    #[test]
+    fn reads_non_ascii_strings() {
+        assert_eq!(strings(r#"name: "Café", other: "日本語""#), ["Café", "日本語"]);
+        assert_eq!(strings(r#""é\"ü""#), [r#"é\"ü"#]);
+    }
+
+    #[test]
+    fn reads_raw_strings() {
+        assert_eq!(strings(r##"#"say "hi" \n"#"##), [r#"say "hi" \n"#]);
+        assert_eq!(strings(r###"##"Café "# —"##"###), [r##"Café "# —"##]);
+    }
+
+    #[test]
+    fn reads_multi_line_strings() {
+        let source = "let s = \"\"\"\n    Café \"quoted\"\n    — done\n    \"\"\"\nlet t = 1";
+        assert_eq!(strings(source), ["\n    Café \"quoted\"\n    — done\n    "]);
+        let tokens = tokenize(source).unwrap();
+        assert_eq!(tokens.last().unwrap().kind, Kind::Other);
+    }
+
+    #[test]
+    fn rejects_unterminated_strings() {
+        assert!(tokenize(r#"name: "Café"#).is_err());
+        assert!(tokenize("\"\"\"\nCafé\n\"\"").is_err());
+    }
+
+    #[test]
+    fn edits_manifests_with_non_ascii_text() {
+        let added = add_dependency(
+            MANIFEST,
+            r#".package(url: "https://github.com/apple/swift-argument-parser", from: "1.0.0")"#,
+        )
+        .unwrap();
+        assert!(has_dependency(&added, "swift-argument-parser").unwrap());
+        assert!(has_dependency(&added, "swift-log").unwrap());
+
+        let added = add_target_dependency(
+            &added,
+            "Café",
+            r#".product(name: "Logging", package: "swift-log")"#,
+        )
+        .unwrap();
+        assert!(added.contains(r#".product(name: "Logging", package: "swift-log")"#));
+
+        let removed = remove_dependency(&added, "swift-log").unwrap();
+        assert!(!has_dependency(&removed, "swift-log").unwrap());
+        assert!(!removed.contains("Logging"));
+        assert!(removed.contains("/* Café — notes /* nested ünïcode */ still a comment */"));
+    }
+}
@@ -0,0 +1,137 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+use tauri::{Emitter, Window};
+use toml_edit::DocumentMut;
+
+use crate::builder::config::FORMAT_VERSION;
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationReport {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub backup_path: PathBuf,
+    pub changes: Vec<String>,
+}
+
+// Migrations edit the document in place, so comments and formatting are kept
+type Migration = fn(&mut DocumentMut, &mut Vec<String>) -> Result<(), String>;
+
+// MIGRATIONS[n] upgrades format version n + 1 to n + 2
+const MIGRATIONS: &[Migration] = &[v1_to_v2];
+
+pub fn format_version(table: &toml::Table) -> Result<u32, String> {
+    table
+        .get("format_version")
+        .and_then(|v| v.as_integer())
+        .and_then(|v| u32::try_from(v).ok())
+        .ok_or("ycode.toml is missing a valid format_version".to_string())
+}
+
+// Upgrades an older ycode.toml one version at a time, keeping a copy of the original next to it.
+// Returns the migrated file, or None when it is already current.
+pub fn migrate(
+    project_path: &Path,
+    content: &str,
+) -> Result<Option<(String, MigrationReport)>, String> {
+    let mut document: DocumentMut = content
+        .parse()
+        .map_err(|e| format!("Failed to parse ycode.toml: {}", e))?;
+
+    let from_version = document
+        .get("format_version")
+        .and_then(|v| v.as_integer())
+        .and_then(|v| u32::try_from(v).ok())
+        .ok_or("ycode.toml is missing a valid format_version".to_string())?;
+    if from_version > FORMAT_VERSION {
+        return Err(format!(
+            "ycode.toml uses format version {}, but this version of YCode only supports up to {}. Update YCode to open this project.",
+            from_version, FORMAT_VERSION
+        ));
+    }
+    if from_version == FORMAT_VERSION {
+        return Ok(None);
+    }
+    if from_version == 0 {
+        return Err("ycode.toml has an invalid format_version of 0".to_string());
+    }
+
+    let mut changes = Vec::new();
+    for version in from_version..FORMAT_VERSION {
+        MIGRATIONS[(version - 1) as usize](&mut document, &mut changes)
+            .map_err(|e| format!("Failed to migrate ycode.toml from version {}: {}", version, e))?;
+        if let Some(value) = document["format_version"].as_value_mut() {
+            let decor = value.decor().clone();
+            *value = ((version + 1) as i64).into();
+            *value.decor_mut() = decor;
+        }
+        changes.push(format!(
+            "Updated format_version from {} to {}",
+            version,
+            version + 1
+        ));
+    }
+
+    // An existing backup may be the only copy of an even older file, so it is never replaced
+    let backup_path = project_path.join(format!("ycode.toml.v{}.bak", from_version));
+    let mut backup = match OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&backup_path)
+    {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+            return Err(format!(
+                "{} already exists, move it away to migrate ycode.toml",
+                backup_path.display()
+            ))
+        }
+        Err(e) => return Err(format!("Failed to back up ycode.toml: {}", e)),
+    };
+    backup
+        .write_all(content.as_bytes())
+        .map_err(|e| format!("Failed to back up ycode.toml: {}", e))?;
+    let migrated = document.to_string();
+    fs::write(project_path.join("ycode.toml"), &migrated)
+        .map_err(|e| format!("Failed to write ycode.toml: {}", e))?;
+
+    Ok(Some((
+        migrated,
+        MigrationReport {
+            from_version,
+            to_version: FORMAT_VERSION,
+            backup_path,
+            changes,
+        },
+    )))
+}
+
+// Version 2 added keys older YCode builds would silently ignore, like [profile], [hooks] and
+// [tasks]. Version 1 files need no changes, the version is raised so those builds refuse new files.
+fn v1_to_v2(_document: &mut DocumentMut, _changes: &mut Vec<String>) -> Result<(), String> {
+    Ok(())
+}
+
+pub fn emit_report(window: &Window, report: &MigrationReport) {
+    window
+        .emit(
+            "build-output",
+            format!(
+                "Migrated ycode.toml from format version {} to {} (backup at {}):",
+                report.from_version,
+                report.to_version,
+                report.backup_path.display()
+            ),
+        )
+        .expect("failed to send output");
+    for change in &report.changes {
+        window
+            .emit("build-output", format!("  {}", change))
+            .expect("failed to send output");
+    }
+}
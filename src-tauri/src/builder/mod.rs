@@ -1,5 +1,20 @@
 pub mod config;
+pub mod coverage;
+pub mod dependencies;
+pub mod diagnostics;
+pub mod frameworks;
+pub mod hooks;
+pub mod info_plist;
+pub mod manifest;
+pub mod manifest_edit;
+pub mod migrate;
 pub mod packer;
+pub mod processes;
 pub mod sdk;
 pub mod swift;
+pub mod tasks;
+pub mod testing;
+pub mod validate;
+pub mod watch;
+pub mod xip;
 pub mod crossplatform;
\ No newline at end of file
@@ -7,13 +7,13 @@ use std::{
 use dircpy::CopyBuilder;
 use zip::write::SimpleFileOptions;
 
-use crate::builder::config::{BuildSettings, ProjectConfig};
+use crate::builder::{
+    config::{BuildSettings, ProjectConfig},
+    frameworks::embed_frameworks,
+    info_plist::build_info_plist,
+};
 
-pub fn pack(
-    project_path: PathBuf,
-    config: &ProjectConfig,
-    build_settings: &BuildSettings,
-) -> Result<PathBuf, String> {
+pub fn pack(project_path: PathBuf, config: &ProjectConfig) -> Result<PathBuf, String> {
     let workdir = project_path.join(".ycode").join("Payload");
     if !workdir.exists() {
         std::fs::create_dir_all(&workdir)
@@ -27,38 +27,24 @@ pub fn pack(
     std::fs::create_dir_all(&app_path)
         .map_err(|e| format!("Failed to create app directory: {}", e))?;
 
-    let exec = project_path
-        .join(".build")
-        .join("arm64-apple-ios")
-        .join(if build_settings.debug {
-            "debug"
-        } else {
-            "release"
-        })
-        .join(&config.product);
+    let products_dir = build_products_dir(&project_path, &config.build);
+    let exec = products_dir.join(&config.product);
 
     if !exec.exists() {
         return Err(format!("Executable not found at: {}", exec.display()));
     }
 
-    fs::copy(exec, app_path.join(&config.product))
+    fs::copy(&exec, app_path.join(&config.product))
         .map_err(|e| format!("Failed to copy executable: {}", e))?;
 
-    // TODO: Create default Info.plist if it doesn't exist
-    let info_plist = project_path.join("Info.plist");
-    if !info_plist.exists() {
-        return Err(format!("Info.plist not found at: {}", info_plist.display()));
-    }
+    embed_frameworks(&exec, &app_path, &products_dir, config)?;
 
-    let info_content = fs::read_to_string(&info_plist)
-        .map_err(|e| format!("Failed to read Info.plist: {}", e))?
-        .replace("[[bundle_id]]", &config.bundle_id)
-        .replace("[[product]]", &config.product)
-        .replace("[[version_num]]", &config.version_num)
-        .replace("[[version_string]]", &config.version_string);
-    fs::write(&app_path.join("Info.plist"), info_content)
+    build_info_plist(&project_path, config)?
+        .to_file_xml(app_path.join("Info.plist"))
         .map_err(|e| format!("Failed to write Info.plist: {}", e))?;
 
+    copy_resource_bundles(&products_dir, &app_path, config)?;
+
     let resources = project_path.join("Resources");
 
     if !resources.exists() {
@@ -73,6 +59,57 @@ pub fn pack(
     Ok(app_path)
 }
 
+pub fn build_products_dir(project_path: &PathBuf, build_settings: &BuildSettings) -> PathBuf {
+    project_path
+        .join(".build")
+        .join("arm64-apple-ios")
+        .join(build_settings.configuration())
+}
+
+// SwiftPM emits a <Package>_<Target> bundle next to the binary for every target with resources,
+// including dependencies. Bundle.module looks for them at the root of the app bundle.
+fn copy_resource_bundles(
+    products_dir: &PathBuf,
+    app_path: &PathBuf,
+    config: &ProjectConfig,
+) -> Result<(), String> {
+    let entries = fs::read_dir(products_dir)
+        .map_err(|e| format!("Failed to read build directory: {}", e))?;
+
+    let mut bundles = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|e| format!("Failed to read build directory entry: {}", e))?
+            .path();
+        let is_bundle = path
+            .extension()
+            .map_or(false, |ext| ext == "bundle" || ext == "resources");
+        if !is_bundle || !path.is_dir() {
+            continue;
+        }
+        let name = path.file_name().unwrap();
+        CopyBuilder::new(&path, &app_path.join(name))
+            .overwrite(true)
+            .run()
+            .map_err(|e| format!("Failed to copy resource bundle {}: {}", path.display(), e))?;
+        bundles.push(path.file_stem().unwrap().to_string_lossy().to_string());
+    }
+
+    for target in &config.resource_targets {
+        let expected = format!("{}_{}", config.package_name, target);
+        if !bundles.contains(&expected) {
+            return Err(format!(
+                "Resource bundle {} for target '{}' not found in {}",
+                expected,
+                target,
+                products_dir.display()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
 pub fn zip_ipa(app: PathBuf, config: &ProjectConfig) -> Result<PathBuf, String> {
     let payload = app.parent().unwrap_or(&PathBuf::from(".")).to_path_buf();
 
@@ -0,0 +1,169 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    process::Command,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use once_cell::sync::OnceCell;
+
+use crate::builder::crossplatform::set_linux_envs;
+
+// Set on every tracked command and inherited by everything it spawns, so the whole tree can be
+// found again, including the processes inside WSL that the Windows side has no handle on
+const RUN_ID_ENV: &str = "YCODE_RUN_ID";
+// How long a cancelled command gets to exit after SIGTERM before it is killed
+const KILL_TIMEOUT: Duration = Duration::from_secs(3);
+
+struct RunningCommand {
+    // What cancels it, usually the label of the window that started it
+    key: String,
+    // None until the command has been spawned
+    pid: Option<u32>,
+    cancelled: bool,
+}
+
+// Running child processes, keyed by run id
+static RUNNING: OnceCell<Mutex<HashMap<String, RunningCommand>>> = OnceCell::new();
+static NEXT_RUN_ID: AtomicU64 = AtomicU64::new(1);
+
+fn running() -> &'static Mutex<HashMap<String, RunningCommand>> {
+    RUNNING.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// Removes the command from the registry when dropped
+pub struct TrackedCommand {
+    run_id: String,
+}
+
+impl TrackedCommand {
+    pub fn cancelled(&self) -> bool {
+        running()
+            .lock()
+            .unwrap()
+            .get(&self.run_id)
+            .is_some_and(|c| c.cancelled)
+    }
+
+    // Records the pid once the command is spawned. If it was cancelled in the meantime it is
+    // terminated right away.
+    pub fn started(&self, pid: u32) {
+        let cancelled = match running().lock().unwrap().get_mut(&self.run_id) {
+            Some(command) => {
+                command.pid = Some(pid);
+                command.cancelled
+            }
+            None => false,
+        };
+        if cancelled {
+            terminate(self.run_id.clone(), pid);
+        }
+    }
+}
+
+impl Drop for TrackedCommand {
+    fn drop(&mut self) {
+        running().lock().unwrap().remove(&self.run_id);
+    }
+}
+
+// Registers the command under `key`. Must be called before the command is spawned, so a cancel
+// in between is not lost.
+pub fn prepare(cmd: &mut Command, key: &str) -> TrackedCommand {
+    let run_id = format!(
+        "{}-{}",
+        std::process::id(),
+        NEXT_RUN_ID.fetch_add(1, Ordering::Relaxed)
+    );
+    set_linux_envs(
+        cmd,
+        &BTreeMap::from([(RUN_ID_ENV.to_string(), run_id.clone())]),
+    );
+    // Put the child in its own process group so the group can be signalled as a whole
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    running().lock().unwrap().insert(
+        run_id.clone(),
+        RunningCommand {
+            key: key.to_string(),
+            pid: None,
+            cancelled: false,
+        },
+    );
+    TrackedCommand { run_id }
+}
+
+// Terminates every process tree started for `key`, returns false if nothing was running
+pub fn cancel(key: &str) -> bool {
+    let mut found = false;
+    let mut spawned = Vec::new();
+    for (run_id, command) in running().lock().unwrap().iter_mut() {
+        if command.key != key {
+            continue;
+        }
+        found = true;
+        command.cancelled = true;
+        if let Some(pid) = command.pid {
+            spawned.push((run_id.clone(), pid));
+        }
+    }
+    for (run_id, pid) in spawned {
+        terminate(run_id, pid);
+    }
+    found
+}
+
+// Asks the process tree to exit and kills it if it is still running after KILL_TIMEOUT
+fn terminate(run_id: String, pid: u32) {
+    kill_tree(&run_id, pid, false);
+    thread::spawn(move || {
+        thread::sleep(KILL_TIMEOUT);
+        if running().lock().unwrap().contains_key(&run_id) {
+            kill_tree(&run_id, pid, true);
+        }
+    });
+}
+
+fn kill_tree(run_id: &str, pid: u32, force: bool) {
+    let signal = if force { "KILL" } else { "TERM" };
+    #[cfg(unix)]
+    {
+        let _ = run_id;
+        Command::new("kill")
+            .arg(format!("-{}", signal))
+            .arg("--")
+            .arg(format!("-{}", pid))
+            .status()
+            .ok();
+    }
+    #[cfg(target_os = "windows")]
+    {
+        // Killing wsl.exe leaves the Linux processes running, so find them by their run id
+        let script = format!(
+            "for f in $(grep -laszx '{}={}' /proc/[0-9]*/environ); do p=${{f#/proc/}}; kill -{} ${{p%/environ}}; done",
+            RUN_ID_ENV, run_id, signal
+        );
+        Command::new("wsl")
+            .args(["bash", "-c", &script])
+            .status()
+            .ok();
+        if force {
+            Command::new("taskkill")
+                .args(["/T", "/F", "/PID", &pid.to_string()])
+                .status()
+                .ok();
+        }
+    }
+}
+
+#[tauri::command]
+pub fn cancel_command(window: tauri::Window) -> bool {
+    cancel(window.label())
+}
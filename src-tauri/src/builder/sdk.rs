@@ -1,32 +1,48 @@
 // Reference: https://github.com/xtool-org/xtool/blob/main/Sources/XToolSupport/SDKBuilder.swift
+use once_cell::sync::OnceCell;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Component, Path, PathBuf};
-use std::process::Command;
-use tauri::{AppHandle, Manager, Window};
+use tauri::{Emitter, Window};
 
 use crate::builder::swift::{SwiftBin, validate_toolchain};
+use crate::builder::xip::extract_entries;
 use crate::builder::crossplatform::{linux_path, linux_temp_dir, read_link, remove_dir_all, symlink};
 use crate::operation::Operation;
 
 #[cfg(target_os = "windows")]
 use crate::windows::{windows_to_wsl_path};
+#[cfg(target_os = "windows")]
+use std::process::Command;
 
 const DARWIN_TOOLS_VERSION: &str = "1.0.1";
+// SHA-256 of toolset-<arch>.tar.gz from the DARWIN_TOOLS_VERSION release, update both together.
+// An empty entry isn't verified, the install warns with the archive's checksum so it can be pinned.
+const TOOLSET_SHA256: &[(&str, &str)] = &[("x86_64", ""), ("aarch64", "")];
 
 #[tauri::command]
 pub async fn install_sdk_operation(
-    app: AppHandle,
     window: Window,
     xcode_path: String,
     toolchain_path: String,
+    // Local toolset-<arch>.tar.gz or extracted toolset directory, for machines without internet
+    toolset_path: Option<String>,
 ) -> Result<(), String> {
     let op = Operation::new("install_sdk".to_string(), &window);
     op.start("create_stage")?;
     let work_dir = op.fail_if_err("create_stage", linux_temp_dir())?.join("DarwinSDKBuild");
-    let res = install_sdk_internal(app, xcode_path, toolchain_path, work_dir.clone(), &op).await;
+    let res = install_sdk_internal(
+        &window,
+        xcode_path,
+        toolchain_path,
+        toolset_path,
+        work_dir.clone(),
+        &op,
+    )
+    .await;
     op.start("cleanup")?;
     let cleanup_result = if work_dir.exists() {
         remove_dir_all(&work_dir)
@@ -59,15 +75,14 @@ pub async fn install_sdk_operation(
     }
 }
 async fn install_sdk_internal(
-    app: AppHandle,
+    window: &Window,
     xcode_path: String,
     toolchain_path: String,
+    toolset_path: Option<String>,
     work_dir: PathBuf,
-    op: &Operation<'_>,
+    op: &Operation,
 ) -> Result<(), String> {
-    if xcode_path.is_empty() || !xcode_path.ends_with(".xip") {
-        return op.fail("create_stage", "Xcode not found".to_string());
-    }
+    let source = op.fail_if_err("create_stage", XcodeSource::detect(&xcode_path))?;
     if toolchain_path.is_empty() {
         return op.fail("create_stage", "Toolchain not found".to_string());
     }
@@ -104,9 +119,23 @@ async fn install_sdk_internal(
     })?;
 
     op.move_on("create_stage", "install_toolset")?;
-    op.fail_if_err("install_toolset", install_toolset(&output_dir).await)?;
+    let unverified = op.fail_if_err(
+        "install_toolset",
+        install_toolset(&output_dir, toolset_path.as_deref().map(Path::new)).await,
+    )?;
+    if let Some(sha256) = unverified {
+        window
+            .emit(
+                "build-output",
+                format!(
+                    "Warning: no checksum is pinned for the toolset, it was installed unverified (SHA-256 {})",
+                    sha256
+                ),
+            )
+            .expect("failed to send output");
+    }
     op.complete("install_toolset")?;
-    let dev = install_developer(&app, &output_dir, &xcode_path, op).await?;
+    let dev = install_developer(&output_dir, &source, op).await?;
     op.start("write_metadata")?;
 
     let iphone_os_sdk = sdk(&dev, "iPhoneOS")?;
@@ -220,6 +249,28 @@ async fn install_sdk_internal(
     Ok(())
 }
 
+// iPhoneOS version of the installed darwin SDK, e.g. "18.2". None when the SDK isn't installed or
+// the toolchain can't show SDK configurations (before Swift 6).
+pub fn installed_iphoneos_version(toolchain_path: &str) -> Option<String> {
+    let swift_bin = SwiftBin::new(toolchain_path).ok()?;
+    let output = swift_bin
+        .output(&[
+            "sdk",
+            "configure",
+            "--show-configuration",
+            "darwin",
+            "arm64-apple-ios",
+        ])
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let regex = Regex::new(r"iPhoneOS(\d+(?:\.\d+)*)\.sdk").ok()?;
+    regex
+        .captures(&String::from_utf8_lossy(&output.stdout))
+        .map(|captures| captures[1].to_string())
+}
+
 fn sdk(dev: &PathBuf, platform: &str) -> Result<String, String> {
     let dir = dev.join(format!("Platforms/{}.platform/Developer/SDKs", platform));
     let regex = Regex::new(&format!(r"^{}\d+\.\d+\.sdk$", regex::escape(platform)))
@@ -240,7 +291,11 @@ fn sdk(dev: &PathBuf, platform: &str) -> Result<String, String> {
     Err(format!("Could not find SDK for {}/{}", platform, platform))
 }
 
-async fn install_toolset(output_path: &PathBuf) -> Result<(), String> {
+// Returns the archive's checksum when there was no pinned one to check it against
+async fn install_toolset(
+    output_path: &PathBuf,
+    local: Option<&Path>,
+) -> Result<Option<String>, String> {
     let toolset_dir = output_path.join("toolset");
     fs::create_dir_all(&toolset_dir)
         .map_err(|e| format!("Failed to create toolset directory: {}", e))?;
@@ -252,25 +307,47 @@ async fn install_toolset(output_path: &PathBuf) -> Result<(), String> {
     } else {
         return Err("Unsupported architecture".to_string());
     };
-    let toolset_url = format!(
-        "https://github.com/xtool-org/darwin-tools-linux-llvm/releases/download/v{}/toolset-{}.tar.gz",
-        DARWIN_TOOLS_VERSION, arch
-    );
-
-    let response = reqwest::get(&toolset_url)
-        .await
-        .map_err(|e| format!("Failed to download toolset: {}", e))?;
-    if !response.status().is_success() {
-        return Err(format!("Failed to download toolset: {}", response.status()));
-    }
-    let tar_gz = response
-        .bytes()
-        .await
-        .map_err(|e| format!("Failed to read response: {}", e))?;
-    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(&*tar_gz));
-    archive
-        .unpack(&toolset_dir)
-        .map_err(|e| format!("Failed to extract toolset: {}", e))?;
+
+    let unverified = match local {
+        // An extracted toolset has nothing to check a checksum against, only its layout
+        Some(dir) if dir.is_dir() => {
+            if !dir.join("bin").join("ld64.lld").exists() {
+                return Err(format!(
+                    "{} is not a toolset directory, bin/ld64.lld is missing",
+                    dir.display()
+                ));
+            }
+            copy_dir(dir, &toolset_dir)?;
+            None
+        }
+        Some(archive) => {
+            let tar_gz = fs::read(archive)
+                .map_err(|e| format!("Failed to read {}: {}", archive.display(), e))?;
+            let unverified = verify_toolset(&tar_gz, arch)?;
+            unpack_toolset(&tar_gz, &toolset_dir)?;
+            unverified
+        }
+        None => {
+            let toolset_url = format!(
+                "https://github.com/xtool-org/darwin-tools-linux-llvm/releases/download/v{}/toolset-{}.tar.gz",
+                DARWIN_TOOLS_VERSION, arch
+            );
+
+            let response = reqwest::get(&toolset_url)
+                .await
+                .map_err(|e| format!("Failed to download toolset: {}", e))?;
+            if !response.status().is_success() {
+                return Err(format!("Failed to download toolset: {}", response.status()));
+            }
+            let tar_gz = response
+                .bytes()
+                .await
+                .map_err(|e| format!("Failed to read response: {}", e))?;
+            let unverified = verify_toolset(&tar_gz, arch)?;
+            unpack_toolset(&tar_gz, &toolset_dir)?;
+            unverified
+        }
+    };
     #[cfg(target_os = "windows")]
     {
         // I'm guessing this has to be done because I'm extracting the tar from windows into the wsl file system and windows doesn't play nice with permissions, but im too lazy to do this properly
@@ -290,84 +367,113 @@ async fn install_toolset(output_path: &PathBuf) -> Result<(), String> {
             ));
         }
     }
-    Ok(())
+    Ok(unverified)
 }
 
-async fn install_developer(
-    app: &AppHandle,
-    output_path: &PathBuf,
-    xcode_path: &str,
-    op: &Operation<'_>,
-) -> Result<PathBuf, String> {
-    op.start("extract_xip")?;
-    let dev_stage = output_path.join("DeveloperStage");
-    op.fail_if_err_map("extract_xip", fs::create_dir_all(&dev_stage), |e| {
-        format!("Failed to create DeveloperStage directory: {}", e)
-    })?;
+// Returns the archive's checksum when none is pinned for `arch`
+fn verify_toolset(tar_gz: &[u8], arch: &str) -> Result<Option<String>, String> {
+    let actual = hex::encode(Sha256::digest(tar_gz));
+    let Some(expected) = TOOLSET_SHA256
+        .iter()
+        .find(|(a, _)| *a == arch)
+        .map(|(_, sha256)| *sha256)
+        .filter(|sha256| !sha256.is_empty())
+    else {
+        return Ok(Some(actual));
+    };
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(format!(
+            "Checksum mismatch for toolset-{}.tar.gz {}: expected {}, got {}",
+            arch, DARWIN_TOOLS_VERSION, expected, actual
+        ));
+    }
+    Ok(None)
+}
 
-    let unxip_path = op.fail_if_err_map(
-        "extract_xip",
-        app.path()
-            .resolve("unxip", tauri::path::BaseDirectory::Resource),
-        |e| format!("Failed to resolve unxip path: {}", e),
-    )?;
+fn unpack_toolset(tar_gz: &[u8], toolset_dir: &Path) -> Result<(), String> {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(tar_gz));
+    archive
+        .unpack(toolset_dir)
+        .map_err(|e| format!("Failed to extract toolset: {}", e))
+}
 
-    #[cfg(target_os = "windows")]
-    let status = Command::new("wsl")
-        .arg("bash")
-        .arg("-c")
-        .arg(format!(
-            "{} {} {}",
-            windows_to_wsl_path(&unxip_path.to_string_lossy()),
-            windows_to_wsl_path(&xcode_path),
-            windows_to_wsl_path(&dev_stage.to_string_lossy())
-        ))
-        .output();
-    #[cfg(not(target_os = "windows"))]
-    let status = Command::new(unxip_path)
-        .current_dir(&dev_stage)
-        .arg(xcode_path)
-        .output();
-    if let Err(e) = status {
-        return op.fail("extract_xip", format!("Failed to run unxip: {}", e));
-    }
-    let status = status.unwrap();
-    if !status.status.success() {
-        return op.fail(
-            "extract_xip",
-            format!(
-                "{}\nProcess exited with code {}",
-                String::from_utf8_lossy(&status.stderr.trim_ascii()),
-                status.status.code().unwrap_or(0)
-            ),
-        );
+// Copies a directory tree, keeping symlinks as symlinks
+fn copy_dir(src: &Path, dst: &Path) -> Result<(), String> {
+    fs::create_dir_all(dst).map_err(|e| format!("Failed to create dir: {}", e))?;
+    for entry in fs::read_dir(src).map_err(|e| format!("Failed to read dir: {}", e))? {
+        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+        let src_path = entry.path();
+        let dst_path = dst.join(entry.file_name());
+        let metadata = fs::symlink_metadata(&src_path)
+            .map_err(|e| format!("Failed to get metadata: {}", e))?;
+        if metadata.file_type().is_symlink() {
+            let target = read_link(&src_path)?;
+            symlink(&target.to_string_lossy().to_string(), &dst_path.to_string_lossy().to_string())
+                .map_err(|e| format!("Failed to create symlink: {}", e))?;
+        } else if metadata.is_dir() {
+            copy_dir(&src_path, &dst_path)?;
+        } else {
+            fs::copy(&src_path, &dst_path).map_err(|e| format!("Failed to copy file: {}", e))?;
+        }
     }
+    Ok(())
+}
 
-    let app_dirs = op
-        .fail_if_err_map("extract_xip", fs::read_dir(&dev_stage), |e| {
-            format!("Failed to read DeveloperStage directory: {}", e)
-        })?
-        .filter_map(Result::ok)
-        .filter(|entry| entry.path().extension().map_or(false, |ext| ext == "app"))
-        .collect::<Vec<_>>();
-    if app_dirs.len() != 1 {
-        return op.fail(
-            "extract_xip",
-            format!(
-                "Expected one .app in DeveloperStage, found {}",
-                app_dirs.len()
-            ),
-        );
+// Where the SDK comes from. Only a .xip has to be extracted, the directories are copied from
+// directly and left untouched.
+enum XcodeSource {
+    Xip(String),
+    App(PathBuf),
+    // Contents/Developer of an Xcode.app, e.g. copied over from a Mac
+    Developer(PathBuf),
+}
+
+impl XcodeSource {
+    fn detect(xcode_path: &str) -> Result<Self, String> {
+        let path = PathBuf::from(xcode_path);
+        if xcode_path.is_empty() || !path.exists() {
+            return Err("Xcode not found".to_string());
+        }
+        if path.is_file() && xcode_path.ends_with(".xip") {
+            return Ok(XcodeSource::Xip(xcode_path.to_string()));
+        }
+        if path.is_dir() {
+            if path.extension().map_or(false, |ext| ext == "app") {
+                if !path.join("Contents").join("Developer").is_dir() {
+                    return Err("Contents/Developer not found in .app".to_string());
+                }
+                return Ok(XcodeSource::App(path));
+            }
+            if path.join("Platforms").is_dir() && path.join("Toolchains").is_dir() {
+                return Ok(XcodeSource::Developer(path));
+            }
+        }
+        Err(format!(
+            "{} is not an Xcode .xip, an Xcode .app or a Developer directory",
+            xcode_path
+        ))
     }
+}
+
+async fn install_developer(
+    output_path: &PathBuf,
+    source: &XcodeSource,
+    op: &Operation,
+) -> Result<PathBuf, String> {
+    op.start("extract_xip")?;
+    let contents_developer = match source {
+        XcodeSource::Xip(xcode_path) => extract_xip(output_path, xcode_path, op)?,
+        XcodeSource::App(app_path) => app_path.join("Contents").join("Developer"),
+        XcodeSource::Developer(dev_path) => dev_path.clone(),
+    };
+    let staged = matches!(source, XcodeSource::Xip(_));
 
     op.move_on("extract_xip", "copy_files")?;
-    let app_path = app_dirs[0].path();
     let dev = output_path.join("Developer");
     op.fail_if_err_map("copy_files", fs::create_dir_all(&dev), |e| {
         format!("Failed to create Developer directory: {}", e)
     })?;
 
-    let contents_developer = app_path.join("Contents").join("Developer");
     if !contents_developer.exists() {
         return op.fail(
             "copy_files",
@@ -377,11 +483,20 @@ async fn install_developer(
 
     op.fail_if_err(
         "copy_files",
-        copy_developer(&contents_developer, &dev, Path::new("Contents/Developer")),
+        copy_developer(
+            &contents_developer,
+            &dev,
+            Path::new("Contents/Developer"),
+            staged,
+        ),
     )?;
-    op.fail_if_err_map("copy_files", remove_dir_all(&dev_stage), |e| {
-        format!("Failed to remove DeveloperStage directory: {}", e)
-    })?;
+    if staged {
+        op.fail_if_err_map(
+            "copy_files",
+            remove_dir_all(&output_path.join("DeveloperStage")),
+            |e| format!("Failed to remove DeveloperStage directory: {}", e),
+        )?;
+    }
 
     for platform in ["iPhoneOS", "MacOSX", "iPhoneSimulator"] {
         let lib = "../../../../../Library";
@@ -425,7 +540,42 @@ async fn install_developer(
     Ok(dev)
 }
 
-fn copy_developer(src: &Path, dst: &Path, rel: &Path) -> Result<(), String> {
+// Extracts the wanted parts of the .xip into DeveloperStage, returns the Contents/Developer in it
+fn extract_xip(output_path: &PathBuf, xcode_path: &str, op: &Operation) -> Result<PathBuf, String> {
+    let dev_stage = output_path.join("DeveloperStage");
+    op.fail_if_err_map("extract_xip", fs::create_dir_all(&dev_stage), |e| {
+        format!("Failed to create DeveloperStage directory: {}", e)
+    })?;
+
+    op.fail_if_err(
+        "extract_xip",
+        extract_entries(Path::new(xcode_path), &dev_stage, is_wanted, |read, total| {
+            op.progress("extract_xip", read, total).ok();
+        }),
+    )?;
+
+    let app_dirs = op
+        .fail_if_err_map("extract_xip", fs::read_dir(&dev_stage), |e| {
+            format!("Failed to read DeveloperStage directory: {}", e)
+        })?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension().map_or(false, |ext| ext == "app"))
+        .collect::<Vec<_>>();
+    if app_dirs.len() != 1 {
+        return op.fail(
+            "extract_xip",
+            format!(
+                "Expected one .app in DeveloperStage, found {}",
+                app_dirs.len()
+            ),
+        );
+    }
+
+    Ok(app_dirs[0].path().join("Contents").join("Developer"))
+}
+
+// With `move_files` the files are moved out of `src` rather than copied, for a staged extraction
+fn copy_developer(src: &Path, dst: &Path, rel: &Path, move_files: bool) -> Result<(), String> {
     for entry in fs::read_dir(src).map_err(|e| format!("Failed to read dir: {}", e))? {
         let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
         let file_name = entry.file_name();
@@ -461,14 +611,19 @@ fn copy_developer(src: &Path, dst: &Path, rel: &Path) -> Result<(), String> {
                 .map_err(|e| format!("Failed to create symlink: {}", e))?;
         } else if metadata.is_dir() {
             fs::create_dir_all(&dst_path).map_err(|e| format!("Failed to create dir: {}", e))?;
-            copy_developer(&src_path, dst, &rel_path)?;
+            copy_developer(&src_path, dst, &rel_path, move_files)?;
         } else if metadata.is_file() {
             if let Some(parent) = dst_path.parent() {
                 fs::create_dir_all(parent)
                     .map_err(|e| format!("Failed to create parent dir: {}", e))?;
             }
-            fs::rename(&src_path, &dst_path)
-                .map_err(|e| format!("Failed to copy file: {}", e))?;
+            if move_files {
+                fs::rename(&src_path, &dst_path)
+                    .map_err(|e| format!("Failed to copy file: {}", e))?;
+            } else {
+                fs::copy(&src_path, &dst_path)
+                    .map_err(|e| format!("Failed to copy file: {}", e))?;
+            }
         }
     }
     Ok(())
@@ -634,7 +789,12 @@ fn is_wanted(path: &Path) -> bool {
         }
     }
 
-    if !wanted_sdk_entry().matches(components.iter().map(|s| s.as_str())) {
+    // Called for every entry of the .xip, so the tree is only built once
+    static WANTED: OnceCell<SDKEntry> = OnceCell::new();
+    if !WANTED
+        .get_or_init(wanted_sdk_entry)
+        .matches(components.iter().map(|s| s.as_str()))
+    {
         return false;
     }
 
@@ -2,23 +2,36 @@
 use crate::windows::has_wsl;
 use crate::{
     builder::{
-        config::{BuildSettings, ProjectConfig},
-        crossplatform::{linux_env, windows_path},
+        config::ProjectConfig,
+        crossplatform::{linux_env, set_linux_envs, windows_path},
+        diagnostics::{BuildDiagnostic, DiagnosticParser, DiagnosticSeverity},
+        frameworks::FRAMEWORKS_RPATH,
+        hooks::{run_hook, Hook},
+        migrate::emit_report,
         packer::{pack, zip_ipa},
+        processes::prepare,
     },
     emit_error_and_return,
+    operation::Operation,
     sideloader::{device::DeviceInfo, sideload::sideload_app},
 };
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::{
     fs::File,
     io::{self, BufRead, BufReader},
     path::PathBuf,
     process::{Command, Output, Stdio},
+    sync::{Arc, Mutex},
     thread,
 };
 use tauri::{Emitter, Window};
 
+// Compile errors included in the failure details of a command
+const MAX_REPORTED_ERRORS: usize = 10;
+
+pub type LineHandler = Arc<Mutex<dyn FnMut(&str) + Send>>;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ToolchainResult {
@@ -114,6 +127,22 @@ impl SwiftBin {
         }
     }
 
+    // Another tool from the toolchain's usr/bin (e.g. llvm-cov), run the same way as swift
+    pub fn tool_command(&self, tool: &str) -> Command {
+        let bin_dir = self.bin_path.rsplit_once('/').map_or("", |(dir, _)| dir);
+        let tool_path = format!("{}/{}", bin_dir, tool);
+        #[cfg(target_os = "windows")]
+        {
+            let mut cmd = Command::new("wsl");
+            cmd.arg(tool_path);
+            cmd
+        }
+        #[cfg(not(target_os = "windows"))]
+        {
+            Command::new(tool_path)
+        }
+    }
+
     pub fn command(&self) -> Command {
         #[cfg(target_os = "windows")]
         {
@@ -285,43 +314,111 @@ fn get_swiftly_path() -> Option<String> {
     None
 }
 
-async fn build_swift_internal(
+// Reports a failed step to the operation and to the console
+fn fail_step<T>(window: &Window, op: &Operation, step: &str, msg: &str) -> Result<T, String> {
+    op.fail::<()>(step, msg.to_string()).ok();
+    emit_error_and_return(window, msg)
+}
+
+// With `compile` false the last build's binary is packed again, for changes to resources only
+pub async fn build_swift_internal(
     window: &Window,
+    op: &Operation,
     folder: &str,
     toolchain_path: &str,
-    build_settings: BuildSettings,
+    profile: &str,
     emit_exit_code: bool,
+    compile: bool,
 ) -> Result<(PathBuf, ProjectConfig), String> {
-    let config = match ProjectConfig::load(PathBuf::from(&folder), &toolchain_path) {
+    op.start("load_config")?;
+    if !validate_toolchain(&toolchain_path) {
+        return fail_step(window, op, "load_config", "Invalid Toolchain");
+    }
+    let config = match ProjectConfig::load(PathBuf::from(&folder), &toolchain_path, profile) {
         Ok(config) => config,
         Err(e) => {
-            return emit_error_and_return(&window, &format!("Failed to load project config: {}", e))
+            return fail_step(
+                window,
+                op,
+                "load_config",
+                &format!("Failed to load project config: {}", e),
+            )
         }
     };
-    let swift_bin = SwiftBin::new(&toolchain_path)?;
+    if let Some(report) = &config.migration {
+        emit_report(window, report);
+    }
+    let swift_bin = op.fail_if_err("load_config", SwiftBin::new(&toolchain_path))?;
+    op.complete("load_config")?;
+    if compile {
+        run_hook(window, op, &config, Hook::PreBuild, None).await?;
+        op.start("compile")?;
+        compile_swift(window, op, folder, &config, &swift_bin, emit_exit_code).await?;
+        op.move_on("compile", "pack")?;
+    } else {
+        // Reported as done so the operation still completes
+        op.complete(Hook::PreBuild.name())?;
+        op.complete("compile")?;
+        op.start("pack")?;
+    }
+
+    let app = match pack(PathBuf::from(&folder), &config) {
+        Ok(app) => app,
+        Err(e) => return fail_step(window, op, "pack", &format!("Failed to pack app: {}", e)),
+    };
+    window
+        .emit("build-output", "Pack Success")
+        .expect("failed to send output");
+    op.complete("pack")?;
+    run_hook(window, op, &config, Hook::PostPack, Some(&app)).await?;
+    Ok((app, config))
+}
+
+async fn compile_swift(
+    window: &Window,
+    op: &Operation,
+    folder: &str,
+    config: &ProjectConfig,
+    swift_bin: &SwiftBin,
+    emit_exit_code: bool,
+) -> Result<(), String> {
     let mut cmd = swift_bin.command();
     cmd.arg("build")
         .arg("-c")
-        .arg(if build_settings.debug {
-            "debug"
-        } else {
-            "release"
-        })
+        .arg(config.build.configuration())
         .arg("--swift-sdk")
         .arg("arm64-apple-ios")
+        .arg("--product")
+        .arg(&config.product)
+        .arg("-Xswiftc")
+        .arg("-target")
+        .arg("-Xswiftc")
+        .arg(format!("arm64-apple-ios{}", config.deployment_target))
+        .arg("-Xlinker")
+        .arg("-rpath")
+        .arg("-Xlinker")
+        .arg(FRAMEWORKS_RPATH)
         .current_dir(&folder);
-
-    pipe_command(&mut cmd, &window, emit_exit_code).await?;
-
-    match pack(PathBuf::from(&folder), &config, &build_settings) {
-        Ok(app) => {
-            window
-                .emit("build-output", "Pack Success")
-                .expect("failed to send output");
-            Ok((app, config))
-        }
-        Err(e) => emit_error_and_return(&window, &format!("Failed to pack app: {}", e)),
+    for flag in &config.build.swiftc_flags {
+        cmd.arg("-Xswiftc").arg(flag);
     }
+    for define in &config.build.defines {
+        cmd.arg("-Xswiftc").arg(format!("-D{}", define));
+    }
+    for flag in &config.build.linker_flags {
+        cmd.arg("-Xlinker").arg(flag);
+    }
+    set_linux_envs(&mut cmd, &config.build.env);
+
+    let compiled = pipe_command_as(
+        op.cancel_key(),
+        &mut cmd,
+        &window,
+        emit_exit_code,
+        Some(compile_progress(op, "compile")?),
+    )
+    .await;
+    op.fail_if_err("compile", compiled)
 }
 
 #[tauri::command]
@@ -329,24 +426,19 @@ pub async fn build_swift(
     window: tauri::Window,
     folder: String,
     toolchain_path: String,
-    debug: bool,
+    profile: String,
 ) -> Result<(), String> {
-    let build_settings = BuildSettings { debug };
-    if !validate_toolchain(&toolchain_path) {
-        return emit_error_and_return(&window, "Invalid Toolchain");
-    }
+    let op = Operation::new("build".to_string(), &window);
 
     let (app, config) =
-        build_swift_internal(&window, &folder, &toolchain_path, build_settings, true).await?;
-
-    let ipa_path = zip_ipa(app, &config);
-    if ipa_path.is_err() {
-        return emit_error_and_return(
-            &window,
-            &format!("Failed to zip IPA: {}", ipa_path.err().unwrap()),
-        );
-    }
-    let ipa_path = ipa_path.unwrap();
+        build_swift_internal(&window, &op, &folder, &toolchain_path, &profile, true, true).await?;
+
+    op.start("zip")?;
+    let ipa_path = match zip_ipa(app.clone(), &config) {
+        Ok(ipa_path) => ipa_path,
+        Err(e) => return fail_step(&window, &op, "zip", &format!("Failed to zip IPA: {}", e)),
+    };
+    op.complete("zip")?;
 
     window
         .emit(
@@ -372,7 +464,44 @@ pub async fn clean_swift(
         .emit("build-output", "Cleaning...")
         .expect("failed to send output");
 
-    pipe_command(&mut cmd, &window, true).await
+    pipe_command(&mut cmd, &window, true, None).await
+}
+
+#[tauri::command]
+pub async fn init_project(
+    window: tauri::Window,
+    folder: String,
+    toolchain_path: String,
+) -> Result<(), String> {
+    if !validate_toolchain(&toolchain_path) {
+        return emit_error_and_return(&window, "Invalid Toolchain");
+    }
+
+    window
+        .emit("build-output", "Initializing YCode project...")
+        .expect("failed to send output");
+
+    let config = match ProjectConfig::init(PathBuf::from(&folder), &toolchain_path) {
+        Ok(config) => config,
+        Err(e) => {
+            return emit_error_and_return(&window, &format!("Failed to initialize project: {}", e))
+        }
+    };
+
+    window
+        .emit(
+            "build-output",
+            format!(
+                "Created ycode.toml for {} ({}), review the bundle id before deploying",
+                config.product, config.bundle_id
+            ),
+        )
+        .expect("failed to send output");
+    window
+        .emit("build-output", "command.done.0")
+        .expect("failed to send output");
+
+    Ok(())
 }
 
 #[tauri::command]
@@ -383,19 +512,18 @@ pub async fn deploy_swift(
     device: DeviceInfo,
     folder: String,
     toolchain_path: String,
-    debug: bool,
+    profile: String,
 ) -> Result<(), String> {
-    let build_settings = BuildSettings { debug };
-    if !validate_toolchain(&toolchain_path) {
-        return emit_error_and_return(&window, "Invalid Toolchain");
-    }
+    let op = Operation::new("deploy".to_string(), &window);
 
     let (app, config) =
-        build_swift_internal(&window, &folder, &toolchain_path, build_settings, false).await?;
+        build_swift_internal(&window, &op, &folder, &toolchain_path, &profile, false, true)
+            .await?;
 
-    sideload_app(&handle, &window, anisette_server, device, app)
+    sideload_app(&handle, &window, &op, anisette_server, device, app.clone())
         .await
         .map_err(|e| format!("Failed to sideload app: {}", e))?;
+    run_hook(&window, &op, &config, Hook::PostInstall, Some(&app)).await?;
 
     window
         .emit("build-output", "Build & Install Success")
@@ -404,41 +532,57 @@ pub async fn deploy_swift(
     Ok(())
 }
 
+// `on_line` sees every line of stdout and stderr before it is sent to the console
 pub async fn pipe_command(
     cmd: &mut Command,
     window: &tauri::Window,
     emit_exit_code: bool,
+    on_line: Option<LineHandler>,
 ) -> Result<(), String> {
-    let name = "build-output";
-    cmd.stdout(Stdio::piped());
-    cmd.stderr(Stdio::piped());
+    pipe_command_as(window.label(), cmd, window, emit_exit_code, on_line).await
+}
 
-    let mut command = match cmd.spawn() {
-        Ok(cmd) => cmd,
+// pipe_command for a command cancelled with `cancel_key` instead of the window label
+pub async fn pipe_command_as(
+    cancel_key: &str,
+    cmd: &mut Command,
+    window: &tauri::Window,
+    emit_exit_code: bool,
+    on_line: Option<LineHandler>,
+) -> Result<(), String> {
+    let name = "build-output";
+    // stdout and stderr share one pipe, so their lines are read in the order they were written
+    let (reader, writer) = match io::pipe() {
+        Ok(pipe) => pipe,
         Err(_) => {
-            return emit_error_and_return(&window, "Failed to spawn build command");
+            return emit_error_and_return(&window, "Failed to create output pipe");
         }
     };
-
-    let stdout = match command.stdout.take() {
-        Some(out) => out,
-        None => {
-            return emit_error_and_return(&window, "Failed to get stdout");
+    let stderr_writer = match writer.try_clone() {
+        Ok(writer) => writer,
+        Err(_) => {
+            return emit_error_and_return(&window, "Failed to create output pipe");
         }
     };
-
-    let stderr = match command.stderr.take() {
-        Some(err) => err,
-        None => {
-            return emit_error_and_return(&window, "Failed to get stderr");
+    cmd.stdout(writer);
+    cmd.stderr(stderr_writer);
+    let tracked = prepare(cmd, cancel_key);
+
+    let spawned = cmd.spawn();
+    // The command keeps its copies of the write end until they are replaced, and the output
+    // only ends once every copy is closed
+    cmd.stdout(Stdio::null());
+    cmd.stderr(Stdio::null());
+    let mut command = match spawned {
+        Ok(cmd) => cmd,
+        Err(_) => {
+            return emit_error_and_return(&window, "Failed to spawn build command");
         }
     };
+    tracked.started(command.id());
 
-    let stdout_handle = spawn_output_thread(stdout, window.clone(), name.to_string());
-    let stderr_handle = spawn_output_thread(stderr, window.clone(), name.to_string());
-
-    stdout_handle.join().expect("stdout thread panicked");
-    stderr_handle.join().expect("stderr thread panicked");
+    let output_handle = spawn_output_thread(reader, window.clone(), name.to_string(), on_line);
+    let errors = output_handle.join().expect("output thread panicked");
 
     let exit_status = match command.wait() {
         Ok(status) => status,
@@ -447,6 +591,13 @@ pub async fn pipe_command(
         }
     };
 
+    if tracked.cancelled() {
+        window
+            .emit(name, "command.cancelled")
+            .expect("failed to send output");
+        return Err("Command cancelled".to_string());
+    }
+
     let exit_code = exit_status.code().unwrap_or(1);
 
     if exit_code != 0 || emit_exit_code {
@@ -456,22 +607,72 @@ pub async fn pipe_command(
     }
 
     if exit_code != 0 {
-        return Err(format!("Command exited with code {}", exit_code));
+        let mut message = format!("Command exited with code {}", exit_code);
+        for error in errors.iter().take(MAX_REPORTED_ERRORS) {
+            message.push('\n');
+            message.push_str(error);
+        }
+        if errors.len() > MAX_REPORTED_ERRORS {
+            message.push_str(&format!(
+                "\n...and {} more errors",
+                errors.len() - MAX_REPORTED_ERRORS
+            ));
+        }
+        return Err(message);
     }
 
     Ok(())
 }
 
+// Reports SwiftPM's [n/m] lines as progress of an operation step
+pub fn compile_progress(op: &Operation, step: &str) -> Result<LineHandler, String> {
+    let regex = Regex::new(r"^\[(\d+)/(\d+)\] ").map_err(|e| format!("Invalid regex: {}", e))?;
+    let op = op.clone();
+    let step = step.to_string();
+    Ok(Arc::new(Mutex::new(move |line: &str| {
+        if let Some(captures) = regex.captures(line) {
+            if let (Ok(completed), Ok(total)) = (captures[1].parse(), captures[2].parse()) {
+                op.progress(&step, completed, total).ok();
+            }
+        }
+    })))
+}
+
+// Forwards output lines to the console and returns the errors found in them
 fn spawn_output_thread<R: std::io::Read + Send + 'static>(
     reader: R,
     window: tauri::Window,
     name: String,
-) -> std::thread::JoinHandle<()> {
+    on_line: Option<LineHandler>,
+) -> std::thread::JoinHandle<Vec<String>> {
     thread::spawn(move || {
         let reader = BufReader::new(reader);
+        let mut parser = DiagnosticParser::new().ok();
+        let mut errors = Vec::new();
+        let mut emit_diagnostic = |diagnostic: BuildDiagnostic| {
+            if diagnostic.severity == DiagnosticSeverity::Error {
+                errors.push(match (&diagnostic.file, diagnostic.line) {
+                    (Some(file), Some(line)) => {
+                        format!("{}:{}: {}", file, line, diagnostic.message)
+                    }
+                    _ => diagnostic.message.clone(),
+                });
+            }
+            window
+                .emit("build-diagnostic", diagnostic)
+                .expect("failed to send diagnostic");
+        };
         for line in reader.lines() {
             match line {
                 Ok(line) => {
+                    if let Some(parser) = parser.as_mut() {
+                        for diagnostic in parser.push_line(&line) {
+                            emit_diagnostic(diagnostic);
+                        }
+                    }
+                    if let Some(on_line) = &on_line {
+                        (on_line.lock().unwrap())(&line);
+                    }
                     window.emit(&name, line).expect("failed to send output");
                 }
                 Err(err) => {
@@ -479,9 +680,13 @@ fn spawn_output_thread<R: std::io::Read + Send + 'static>(
                         .emit(&name, "command.done.999".to_string())
                         .expect("failed to send output");
                     eprintln!("Error reading output: {}", err);
-                    return;
+                    break;
                 }
             }
         }
+        if let Some(diagnostic) = parser.as_mut().and_then(|p| p.finish()) {
+            emit_diagnostic(diagnostic);
+        }
+        errors
     })
 }
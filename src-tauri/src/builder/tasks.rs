@@ -0,0 +1,175 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use regex::Regex;
+use serde::Serialize;
+use tauri::{Emitter, Window};
+
+use crate::{
+    builder::{
+        config::{load_tasks, TaskConfig},
+        crossplatform::{linux_command, set_linux_envs, windows_path},
+        diagnostics::{severity, BuildDiagnostic, DiagnosticSeverity},
+        swift::{pipe_command, LineHandler},
+    },
+    emit_error_and_return,
+};
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskInfo {
+    pub name: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub cwd: Option<String>,
+    pub depends_on: Vec<String>,
+}
+
+// The task with its dependencies before it, each task only once
+fn run_order(tasks: &BTreeMap<String, TaskConfig>, name: &str) -> Result<Vec<String>, String> {
+    let mut order = Vec::new();
+    visit(tasks, name, &mut Vec::new(), &mut order)?;
+    Ok(order)
+}
+
+fn visit(
+    tasks: &BTreeMap<String, TaskConfig>,
+    name: &str,
+    stack: &mut Vec<String>,
+    order: &mut Vec<String>,
+) -> Result<(), String> {
+    if order.iter().any(|n| n == name) {
+        return Ok(());
+    }
+    if let Some(start) = stack.iter().position(|n| n == name) {
+        let mut cycle = stack[start..].to_vec();
+        cycle.push(name.to_string());
+        return Err(format!("Task dependency cycle: {}", cycle.join(" -> ")));
+    }
+    let task = tasks.get(name).ok_or_else(|| match stack.last() {
+        Some(parent) => format!("Task \"{}\" depends on unknown task \"{}\"", parent, name),
+        None => format!("Unknown task \"{}\"", name),
+    })?;
+
+    stack.push(name.to_string());
+    for dependency in &task.depends_on {
+        visit(tasks, dependency, stack, order)?;
+    }
+    stack.pop();
+    order.push(name.to_string());
+    Ok(())
+}
+
+// Lines matching the task's problem_matcher are reported like compiler diagnostics. Swift
+// diagnostics are already picked up by pipe_command.
+fn problem_matcher(window: &Window, pattern: &str, cwd: &Path) -> Result<LineHandler, String> {
+    let regex = Regex::new(pattern).map_err(|e| format!("Invalid problem matcher: {}", e))?;
+    if !regex.capture_names().any(|name| name == Some("message")) {
+        return Err("Problem matcher has no `message` group".to_string());
+    }
+    let window = window.clone();
+    let cwd = cwd.to_path_buf();
+    Ok(Arc::new(Mutex::new(move |line: &str| {
+        let Some(captures) = regex.captures(line) else {
+            return;
+        };
+        let group = |name: &str| captures.name(name).map(|m| m.as_str());
+        let file = group("file").map(|file| {
+            if file.starts_with('/') {
+                windows_path(file)
+            } else {
+                cwd.join(file).to_string_lossy().to_string()
+            }
+        });
+        let diagnostic = BuildDiagnostic {
+            severity: group("severity")
+                .map_or(DiagnosticSeverity::Error, |s| severity(&s.to_lowercase())),
+            message: group("message").unwrap_or_default().to_string(),
+            file,
+            line: group("line").and_then(|l| l.parse().ok()),
+            column: group("column").and_then(|c| c.parse().ok()),
+            snippet: Vec::new(),
+            fix_its: Vec::new(),
+            notes: Vec::new(),
+        };
+        window
+            .emit("build-diagnostic", diagnostic)
+            .expect("failed to send diagnostic");
+    })))
+}
+
+#[tauri::command]
+pub async fn list_tasks(folder: String) -> Result<Vec<TaskInfo>, String> {
+    let tasks = load_tasks(PathBuf::from(&folder))?;
+    Ok(tasks
+        .into_iter()
+        .map(|(name, task)| TaskInfo {
+            name,
+            command: task.command,
+            args: task.args,
+            cwd: task.cwd,
+            depends_on: task.depends_on,
+        })
+        .collect())
+}
+
+// Runs the task after its dependencies, stopping at the first one that fails. Cancelled with
+// cancel_command like any other command.
+#[tauri::command]
+pub async fn run_task(window: Window, folder: String, name: String) -> Result<(), String> {
+    let project_path = PathBuf::from(&folder);
+    let tasks = match load_tasks(project_path.clone()) {
+        Ok(tasks) => tasks,
+        Err(e) => return emit_error_and_return(&window, &format!("Failed to load tasks: {}", e)),
+    };
+    let order = match run_order(&tasks, &name) {
+        Ok(order) => order,
+        Err(e) => return emit_error_and_return(&window, &e),
+    };
+
+    // Problem matchers are checked before anything runs
+    let mut runs = Vec::new();
+    for task_name in &order {
+        let task = &tasks[task_name];
+        let cwd = match &task.cwd {
+            Some(cwd) => project_path.join(cwd),
+            None => project_path.clone(),
+        };
+        let on_line = match &task.problem_matcher {
+            Some(pattern) => match problem_matcher(&window, pattern, &cwd) {
+                Ok(handler) => Some(handler),
+                Err(e) => {
+                    return emit_error_and_return(
+                        &window,
+                        &format!("Task \"{}\": {}", task_name, e),
+                    )
+                }
+            },
+            None => None,
+        };
+        runs.push((task_name, task, cwd, on_line));
+    }
+
+    let last = runs.len() - 1;
+    for (i, (task_name, task, cwd, on_line)) in runs.into_iter().enumerate() {
+        let mut command_line = vec![task.command.clone()];
+        command_line.extend(task.args.iter().cloned());
+        window
+            .emit(
+                "build-output",
+                format!("Running task {}: {}", task_name, command_line.join(" ")),
+            )
+            .expect("failed to send output");
+
+        let mut cmd = linux_command(&task.command);
+        cmd.args(&task.args).current_dir(&cwd);
+        set_linux_envs(&mut cmd, &task.env);
+        pipe_command(&mut cmd, &window, i == last, on_line)
+            .await
+            .map_err(|e| format!("Task \"{}\" failed: {}", task_name, e))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,410 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use regex::Regex;
+use serde::Serialize;
+use tauri::{Emitter, Window};
+
+use crate::{
+    builder::{
+        coverage::{self, CoverageReport},
+        manifest::load_package_model,
+        swift::{pipe_command, validate_toolchain, SwiftBin},
+    },
+    emit_error_and_return,
+};
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum TestFramework {
+    XcTest,
+    SwiftTesting,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum TestStatus {
+    Started,
+    Passed,
+    Failed,
+    Skipped,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TestFailure {
+    pub message: String,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TestEvent {
+    pub name: String,
+    pub framework: TestFramework,
+    pub status: TestStatus,
+    // Seconds, for finished tests
+    pub duration: Option<f64>,
+    pub failures: Vec<TestFailure>,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TestSummary {
+    pub passed: u32,
+    pub failed: u32,
+    pub skipped: u32,
+    pub failed_tests: Vec<String>,
+    // Only collected for runs with coverage enabled
+    pub coverage: Option<CoverageReport>,
+}
+
+// Turns `swift test` output into per-test events. XCTest prints "Test Case 'Class.test' ..." (or
+// '-[Module.Class test]' on macOS), swift-testing prints "<symbol> Test name() ...".
+struct TestParser {
+    xctest_case: Regex,
+    xctest_failure: Regex,
+    xctest_skip: Regex,
+    testing_started: Regex,
+    testing_finished: Regex,
+    testing_skipped: Regex,
+    testing_issue: Regex,
+    failures: HashMap<String, Vec<TestFailure>>,
+    summary: TestSummary,
+    // SwiftPM --filter patterns of the failed XCTest tests. swift-testing only prints display
+    // names, its failed tests are taken from the event stream instead.
+    failed_filters: Vec<String>,
+}
+
+impl TestParser {
+    fn new() -> Result<Self, String> {
+        let regex = |pattern: &str| Regex::new(pattern).map_err(|e| format!("Invalid regex: {}", e));
+        Ok(TestParser {
+            xctest_case: regex(
+                r"^Test Case '(?:-\[(?P<class>\S+) (?P<method>\S+)\]|(?P<name>[^']+))' (?P<status>started|passed|failed|skipped)(?: \((?P<duration>[\d.]+) seconds\))?",
+            )?,
+            xctest_failure: regex(
+                r"^(?P<file>.+?):(?P<line>\d+): error: (?:-\[(?P<class>\S+) (?P<method>\S+)\]|(?P<name>\S+)) : (?P<message>.*)$",
+            )?,
+            xctest_skip: regex(
+                r"^(?P<file>.+?):(?P<line>\d+): (?:-\[(?P<class>\S+) (?P<method>\S+)\]|(?P<name>\S+)) : Test skipped(?: - (?P<message>.*))?$",
+            )?,
+            testing_started: regex(r"^\S+ Test (?P<name>.+?) started\.$")?,
+            testing_finished: regex(
+                r"^\S+ Test (?P<name>.+?) (?P<status>passed|failed) after (?P<duration>[\d.]+) seconds",
+            )?,
+            testing_skipped: regex(r"^\S+ Test (?P<name>.+?) skipped")?,
+            testing_issue: regex(
+                r"^\S+ Test (?P<name>.+?) recorded an issue at (?P<file>[^:]+):(?P<line>\d+):(?P<column>\d+): (?P<message>.*)$",
+            )?,
+            failures: HashMap::new(),
+            summary: TestSummary::default(),
+            failed_filters: Vec::new(),
+        })
+    }
+
+    fn push_line(&mut self, line: &str) -> Option<TestEvent> {
+        if let Some(captures) = self.xctest_case.captures(line) {
+            let name = xctest_name(&captures);
+            let status = match &captures["status"] {
+                "started" => TestStatus::Started,
+                "passed" => TestStatus::Passed,
+                "failed" => TestStatus::Failed,
+                _ => TestStatus::Skipped,
+            };
+            let duration = captures.name("duration").and_then(|d| d.as_str().parse().ok());
+            // Filters match "Module.Class/test"
+            let filter = match (captures.name("class"), captures.name("method")) {
+                (Some(class), Some(method)) => {
+                    format!("{}/{}$", regex::escape(class.as_str()), regex::escape(method.as_str()))
+                }
+                _ => match name.rsplit_once('.') {
+                    Some((class, method)) => {
+                        format!("{}/{}$", regex::escape(class), regex::escape(method))
+                    }
+                    None => format!("{}$", regex::escape(&name)),
+                },
+            };
+            return Some(self.event(name, TestFramework::XcTest, status, duration, Some(filter)));
+        }
+        if let Some(captures) = self.xctest_skip.captures(line) {
+            let name = xctest_name(&captures);
+            self.failures.entry(name).or_default().push(TestFailure {
+                message: captures
+                    .name("message")
+                    .map_or("Test skipped".to_string(), |m| m.as_str().to_string()),
+                file: Some(captures["file"].to_string()),
+                line: captures["line"].parse().ok(),
+                column: None,
+            });
+            return None;
+        }
+        if let Some(captures) = self.xctest_failure.captures(line) {
+            let name = xctest_name(&captures);
+            self.failures.entry(name).or_default().push(TestFailure {
+                message: captures["message"].trim_end_matches(" - ").to_string(),
+                file: Some(captures["file"].to_string()),
+                line: captures["line"].parse().ok(),
+                column: None,
+            });
+            return None;
+        }
+
+        if let Some(captures) = self.testing_issue.captures(line) {
+            self.failures
+                .entry(captures["name"].to_string())
+                .or_default()
+                .push(TestFailure {
+                    message: captures["message"].to_string(),
+                    file: Some(captures["file"].to_string()),
+                    line: captures["line"].parse().ok(),
+                    column: captures["column"].parse().ok(),
+                });
+            return None;
+        }
+        let (captures, status) = if let Some(captures) = self.testing_started.captures(line) {
+            (captures, TestStatus::Started)
+        } else if let Some(captures) = self.testing_finished.captures(line) {
+            let status = if &captures["status"] == "passed" {
+                TestStatus::Passed
+            } else {
+                TestStatus::Failed
+            };
+            (captures, status)
+        } else if let Some(captures) = self.testing_skipped.captures(line) {
+            (captures, TestStatus::Skipped)
+        } else {
+            return None;
+        };
+        let name = captures["name"].to_string();
+        // "Test run with 3 tests passed after ..." is the summary of the whole run
+        if name.starts_with("run with ") {
+            return None;
+        }
+        let duration = captures.name("duration").and_then(|d| d.as_str().parse().ok());
+        Some(self.event(name, TestFramework::SwiftTesting, status, duration, None))
+    }
+
+    fn event(
+        &mut self,
+        name: String,
+        framework: TestFramework,
+        status: TestStatus,
+        duration: Option<f64>,
+        filter: Option<String>,
+    ) -> TestEvent {
+        let failures = match status {
+            TestStatus::Started => Vec::new(),
+            _ => self.failures.remove(&name).unwrap_or_default(),
+        };
+        match status {
+            TestStatus::Passed => self.summary.passed += 1,
+            TestStatus::Failed => {
+                self.summary.failed += 1;
+                self.summary.failed_tests.push(name.clone());
+                self.failed_filters.extend(filter);
+            }
+            TestStatus::Skipped => self.summary.skipped += 1,
+            TestStatus::Started => {}
+        }
+        TestEvent {
+            name,
+            framework,
+            status,
+            duration,
+            failures,
+        }
+    }
+}
+
+// "Class.test" on Linux, "Module.Class test" for '-[Module.Class test]' on macOS
+fn xctest_name(captures: &regex::Captures) -> String {
+    match captures.name("name") {
+        Some(name) => name.as_str().to_string(),
+        None => format!("{} {}", &captures["class"], &captures["method"]),
+    }
+}
+
+fn failed_tests_path(project_path: &Path) -> PathBuf {
+    project_path.join(".ycode").join("failed-tests.json")
+}
+
+// Relative to the project, so it is the same path inside WSL
+const EVENT_STREAM_PATH: &str = ".ycode/test-events.jsonl";
+
+// swift-testing's JSON event stream, added in Swift 6
+fn supports_event_stream(swift_bin: &SwiftBin, project_path: &Path) -> bool {
+    swift_bin
+        .command()
+        .arg("test")
+        .arg("--help")
+        .current_dir(project_path)
+        .output()
+        .is_ok_and(|output| {
+            String::from_utf8_lossy(&output.stdout).contains("--experimental-event-stream-output")
+        })
+}
+
+// --filter patterns for the swift-testing tests that recorded an issue. Their ids look like
+// "Module.Suite/test()/File.swift:12:4", the filter matches the part before the source location.
+fn failed_testing_filters(project_path: &Path) -> Result<Vec<String>, String> {
+    let content = match std::fs::read_to_string(project_path.join(EVENT_STREAM_PATH)) {
+        Ok(content) => content,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let location = Regex::new(r"/[^/]+:\d+:\d+$").map_err(|e| format!("Invalid regex: {}", e))?;
+    let mut filters = Vec::new();
+    for line in content.lines() {
+        let Ok(record) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        let payload = &record["payload"];
+        if record["kind"] != "event"
+            || payload["kind"] != "issueRecorded"
+            || payload["issue"]["isKnown"] == true
+        {
+            continue;
+        }
+        let Some(id) = payload["testID"].as_str() else {
+            continue;
+        };
+        let filter = regex::escape(&location.replace(id, ""));
+        if !filters.contains(&filter) {
+            filters.push(filter);
+        }
+    }
+    Ok(filters)
+}
+
+// Runs the package's tests on the host. `filter` takes SwiftPM --filter patterns, `only_failed`
+// re-runs the tests that failed in the previous run instead. With `coverage` the line coverage
+// of the run is exported to .ycode/coverage and emitted as a coverage-report event.
+#[tauri::command]
+pub async fn test_swift(
+    window: Window,
+    folder: String,
+    toolchain_path: String,
+    filter: Vec<String>,
+    only_failed: bool,
+    coverage: bool,
+) -> Result<TestSummary, String> {
+    if !validate_toolchain(&toolchain_path) {
+        return emit_error_and_return(&window, "Invalid Toolchain");
+    }
+    let project_path = PathBuf::from(&folder);
+
+    let filter = if only_failed {
+        let failed: Vec<String> = std::fs::read_to_string(failed_tests_path(&project_path))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        if failed.is_empty() {
+            return emit_error_and_return(&window, "No failed tests to re-run");
+        }
+        failed
+    } else {
+        filter
+    };
+
+    let swift_bin = SwiftBin::new(&toolchain_path)?;
+    let mut cmd = swift_bin.command();
+    cmd.arg("test").current_dir(&folder);
+    for pattern in &filter {
+        cmd.arg("--filter").arg(pattern);
+    }
+    if coverage {
+        cmd.arg("--enable-code-coverage");
+    }
+    std::fs::create_dir_all(project_path.join(".ycode"))
+        .map_err(|e| format!("Failed to create .ycode directory: {}", e))?;
+    let event_stream = supports_event_stream(&swift_bin, &project_path);
+    if event_stream {
+        std::fs::remove_file(project_path.join(EVENT_STREAM_PATH)).ok();
+        cmd.arg("--experimental-event-stream-output")
+            .arg(EVENT_STREAM_PATH)
+            .arg("--experimental-event-stream-version")
+            .arg("0");
+    }
+
+    let parser = Arc::new(Mutex::new(TestParser::new()?));
+    let handler_parser = parser.clone();
+    let handler_window = window.clone();
+    let result = pipe_command(
+        &mut cmd,
+        &window,
+        true,
+        Some(Arc::new(Mutex::new(move |line: &str| {
+            if let Some(event) = handler_parser.lock().unwrap().push_line(line) {
+                handler_window
+                    .emit("test-event", event)
+                    .expect("failed to send test event");
+            }
+        }))),
+    )
+    .await;
+
+    let (mut summary, mut failed_filters) = {
+        let parser = parser.lock().unwrap();
+        (parser.summary.clone(), parser.failed_filters.clone())
+    };
+    let ran_tests = summary.passed + summary.failed + summary.skipped > 0;
+    // A failing test run exits with an error too, only report it when no tests ran at all
+    if let (Err(e), false) = (&result, ran_tests) {
+        return Err(e.clone());
+    }
+
+    if event_stream {
+        failed_filters.extend(failed_testing_filters(&project_path)?);
+    }
+    let content = serde_json::to_string(&failed_filters).map_err(|e| e.to_string())?;
+    std::fs::write(failed_tests_path(&project_path), content)
+        .map_err(|e| format!("Failed to save failed tests: {}", e))?;
+
+    window
+        .emit(
+            "build-output",
+            format!(
+                "Tests finished: {} passed, {} failed, {} skipped",
+                summary.passed, summary.failed, summary.skipped
+            ),
+        )
+        .expect("failed to send output");
+
+    if coverage {
+        // The test results are still reported when the coverage can't be
+        let report = load_package_model(&project_path, &toolchain_path)
+            .and_then(|package| coverage::collect(&project_path, &swift_bin, &package));
+        match report {
+            Ok(report) => {
+                window
+                    .emit(
+                        "build-output",
+                        format!(
+                            "Coverage: {:.1}% of lines ({}/{}), reports written to {}",
+                            report.percent,
+                            report.lines_covered,
+                            report.lines_total,
+                            project_path.join(".ycode").join("coverage").display()
+                        ),
+                    )
+                    .expect("failed to send output");
+                window
+                    .emit("coverage-report", report.clone())
+                    .expect("failed to send coverage report");
+                summary.coverage = Some(report);
+            }
+            Err(e) => {
+                window
+                    .emit("build-output", format!("Failed to collect coverage: {}", e))
+                    .expect("failed to send output");
+            }
+        }
+    }
+
+    Ok(summary)
+}
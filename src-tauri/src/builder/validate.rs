@@ -0,0 +1,731 @@
+use std::ops::Range;
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{json, Map, Value as JsonValue};
+use toml::{
+    de::{DeString, DeTable, DeValue},
+    Spanned,
+};
+
+use crate::builder::{
+    config::{DEVICE_FAMILIES, FORMAT_VERSION, ORIENTATIONS},
+    migrate::format_version,
+};
+
+const BUNDLE_ID_PATTERN: &str = r"^[A-Za-z0-9-]+(\.[A-Za-z0-9-]+)+$";
+const BUNDLE_ID_SUFFIX_PATTERN: &str = r"^[A-Za-z0-9.-]*$";
+const VERSION_PATTERN: &str = r"^[0-9]+(\.[0-9]+){0,2}$";
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigDiagnostic {
+    pub severity: Severity,
+    pub message: String,
+    // 1-based, with columns in UTF-16 code units like the editor
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl std::fmt::Display for ConfigDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ycode.toml:{}:{}: {}",
+            self.start_line, self.start_column, self.message
+        )
+    }
+}
+
+enum Format {
+    BundleId,
+    BundleIdSuffix,
+    Version,
+}
+
+enum Kind {
+    Integer,
+    String(Option<Format>),
+    StringArray(Option<&'static [&'static str]>),
+    StringMap,
+    // Free-form table, e.g. [info]
+    AnyTable,
+    Table(&'static [Field]),
+    // Table of user-named tables, e.g. [profile.<name>]
+    NamedTables(&'static [Field]),
+}
+
+struct Field {
+    key: &'static str,
+    kind: Kind,
+    required: bool,
+    description: &'static str,
+}
+
+const fn field(key: &'static str, kind: Kind, description: &'static str) -> Field {
+    Field {
+        key,
+        kind,
+        required: false,
+        description,
+    }
+}
+
+const fn required(key: &'static str, kind: Kind, description: &'static str) -> Field {
+    Field {
+        key,
+        kind,
+        required: true,
+        description,
+    }
+}
+
+const ROOT: &[Field] = &[
+    required(
+        "format_version",
+        Kind::Integer,
+        "Version of the ycode.toml format. Older versions are migrated automatically.",
+    ),
+    required("project", Kind::Table(PROJECT), "Project settings"),
+    field(
+        "info",
+        Kind::AnyTable,
+        "Keys merged into the generated Info.plist. Strings may use [[key]] placeholders.",
+    ),
+    field(
+        "profile",
+        Kind::NamedTables(PROFILE),
+        "Build profiles. \"debug\" and \"release\" are built in, custom profiles must set `inherits`.",
+    ),
+    field(
+        "hooks",
+        Kind::Table(HOOKS),
+        "Shell commands run in the project directory during a build. A non-zero exit stops it.",
+    ),
+    field(
+        "tasks",
+        Kind::NamedTables(TASK),
+        "Commands run with Run Task, e.g. linters or code generators",
+    ),
+];
+
+const PROJECT: &[Field] = &[
+    required(
+        "version_num",
+        Kind::String(Some(Format::Version)),
+        "CFBundleVersion, e.g. \"1\"",
+    ),
+    required(
+        "version_string",
+        Kind::String(Some(Format::Version)),
+        "CFBundleShortVersionString, e.g. \"1.0.0\"",
+    ),
+    required(
+        "bundle_id",
+        Kind::String(Some(Format::BundleId)),
+        "Bundle identifier, e.g. \"com.example.myapp\"",
+    ),
+    field(
+        "deployment_target",
+        Kind::String(Some(Format::Version)),
+        "Minimum iOS version. Defaults to the iOS platform in Package.swift.",
+    ),
+    field(
+        "product",
+        Kind::String(None),
+        "Executable product to package. Required when the package has more than one.",
+    ),
+    field(
+        "display_name",
+        Kind::String(None),
+        "Name shown on the home screen. Defaults to the product name.",
+    ),
+    field(
+        "device_family",
+        Kind::StringArray(Some(DEVICE_FAMILIES)),
+        "Supported devices",
+    ),
+    field(
+        "orientations",
+        Kind::StringArray(Some(ORIENTATIONS)),
+        "Supported interface orientations on iPhone",
+    ),
+    field(
+        "ipad_orientations",
+        Kind::StringArray(Some(ORIENTATIONS)),
+        "Supported interface orientations on iPad",
+    ),
+];
+
+const PROFILE: &[Field] = &[
+    field(
+        "inherits",
+        Kind::String(None),
+        "Profile to start from: \"debug\", \"release\" or another custom profile",
+    ),
+    field(
+        "swiftc_flags",
+        Kind::StringArray(None),
+        "Extra flags passed to swiftc",
+    ),
+    field(
+        "linker_flags",
+        Kind::StringArray(None),
+        "Extra flags passed to the linker",
+    ),
+    field(
+        "defines",
+        Kind::StringArray(None),
+        "Swift compilation conditions, passed as -D<NAME>",
+    ),
+    field(
+        "env",
+        Kind::StringMap,
+        "Environment variables for the build",
+    ),
+    field(
+        "bundle_id_suffix",
+        Kind::String(Some(Format::BundleIdSuffix)),
+        "Appended to project.bundle_id, e.g. \".beta\"",
+    ),
+    field(
+        "display_name",
+        Kind::String(None),
+        "Overrides project.display_name",
+    ),
+    field(
+        "info",
+        Kind::AnyTable,
+        "Keys merged into Info.plist on top of [info]",
+    ),
+];
+
+const HOOKS: &[Field] = &[
+    field(
+        "pre_build",
+        Kind::String(None),
+        "Run before compiling. Gets YCODE_PROJECT_DIR, YCODE_PRODUCT, YCODE_BUNDLE_ID, YCODE_PROFILE and YCODE_CONFIGURATION.",
+    ),
+    field(
+        "post_pack",
+        Kind::String(None),
+        "Run after the app is packed and before the .ipa is zipped, with YCODE_APP_PATH",
+    ),
+    field(
+        "post_install",
+        Kind::String(None),
+        "Run after the app is installed on a device, with YCODE_APP_PATH",
+    ),
+];
+
+const TASK: &[Field] = &[
+    required("command", Kind::String(None), "Program to run"),
+    field("args", Kind::StringArray(None), "Arguments passed to the program"),
+    field(
+        "cwd",
+        Kind::String(None),
+        "Working directory, relative to the project. Defaults to the project directory.",
+    ),
+    field("env", Kind::StringMap, "Environment variables for the task"),
+    field(
+        "depends_on",
+        Kind::StringArray(None),
+        "Tasks to run first, in order. Each task runs at most once.",
+    ),
+    field(
+        "problem_matcher",
+        Kind::String(None),
+        "Regex for problems in the output, with named groups `message` and optionally `file`, `line`, `column` and `severity`. Swift diagnostics are always recognized.",
+    ),
+];
+
+impl Format {
+    fn pattern(&self) -> &'static str {
+        match self {
+            Format::BundleId => BUNDLE_ID_PATTERN,
+            Format::BundleIdSuffix => BUNDLE_ID_SUFFIX_PATTERN,
+            Format::Version => VERSION_PATTERN,
+        }
+    }
+
+    fn expected(&self) -> &'static str {
+        match self {
+            Format::BundleId => {
+                "a reverse-DNS identifier of letters, digits and hyphens, e.g. com.example.myapp"
+            }
+            Format::BundleIdSuffix => "only letters, digits, hyphens and periods",
+            Format::Version => "one to three period-separated numbers, e.g. 1.0.0",
+        }
+    }
+}
+
+impl Kind {
+    fn name(&self) -> &'static str {
+        match self {
+            Kind::Integer => "an integer",
+            Kind::String(_) => "a string",
+            Kind::StringArray(_) => "an array of strings",
+            Kind::StringMap | Kind::AnyTable | Kind::Table(_) | Kind::NamedTables(_) => "a table",
+        }
+    }
+}
+
+struct Validator<'a> {
+    content: &'a str,
+    diagnostics: Vec<ConfigDiagnostic>,
+    bundle_id: Regex,
+    bundle_id_suffix: Regex,
+    version: Regex,
+}
+
+// Checks ycode.toml against the current format and reports every problem with its location
+pub fn validate(content: &str) -> Result<Vec<ConfigDiagnostic>, String> {
+    let regex = |pattern: &str| Regex::new(pattern).map_err(|e| format!("Invalid regex: {}", e));
+    let mut validator = Validator {
+        content,
+        diagnostics: Vec::new(),
+        bundle_id: regex(BUNDLE_ID_PATTERN)?,
+        bundle_id_suffix: regex(BUNDLE_ID_SUFFIX_PATTERN)?,
+        version: regex(VERSION_PATTERN)?,
+    };
+
+    let (root, errors) = DeTable::parse_recoverable(content);
+    if !errors.is_empty() {
+        for error in errors {
+            validator.push(
+                Severity::Error,
+                error.span().unwrap_or(0..0),
+                error.message().to_string(),
+            );
+        }
+        return Ok(validator.diagnostics);
+    }
+
+    // Files in an older format are migrated when the project loads, so only the current format is checked
+    let version_span = root
+        .get_ref()
+        .iter()
+        .find(|(k, _)| key_str(k) == "format_version")
+        .map(|(_, v)| v.span());
+    let version = toml::from_str::<toml::Table>(content)
+        .map_err(|e| e.to_string())
+        .and_then(|table| format_version(&table));
+    match (version, version_span) {
+        (Ok(version), Some(span)) if version > FORMAT_VERSION => {
+            validator.push(
+                Severity::Error,
+                span,
+                format!(
+                    "Format version {} is newer than the latest supported version {}",
+                    version, FORMAT_VERSION
+                ),
+            );
+            return Ok(validator.diagnostics);
+        }
+        (Ok(version), Some(span)) if version < FORMAT_VERSION => {
+            validator.push(
+                Severity::Warning,
+                span,
+                format!(
+                    "Format version {} will be migrated to version {} when the project is loaded",
+                    version, FORMAT_VERSION
+                ),
+            );
+            return Ok(validator.diagnostics);
+        }
+        _ => {}
+    }
+
+    validator.check_table(root.get_ref(), ROOT, "", 0..0);
+    validator
+        .diagnostics
+        .sort_by_key(|d| (d.start_line, d.start_column));
+    Ok(validator.diagnostics)
+}
+
+// Errors from validate() as a single message, used when loading the project
+pub fn check(content: &str) -> Result<(), String> {
+    let errors: Vec<String> = validate(content)?
+        .into_iter()
+        .filter(|d| d.severity == Severity::Error)
+        .map(|d| d.to_string())
+        .collect();
+    if !errors.is_empty() {
+        return Err(format!("Invalid ycode.toml:\n{}", errors.join("\n")));
+    }
+    Ok(())
+}
+
+impl Validator<'_> {
+    fn check_table(&mut self, table: &DeTable, fields: &[Field], path: &str, span: Range<usize>) {
+        for (key, value) in table.iter() {
+            let key_path = join(path, key_str(key));
+            match fields.iter().find(|f| f.key == key_str(key)) {
+                Some(field) => self.check_value(value, &field.kind, &key_path, key.span()),
+                None => {
+                    let suggestion = fields
+                        .iter()
+                        .filter(|f| edit_distance(f.key, key_str(key)) <= 2)
+                        .min_by_key(|f| edit_distance(f.key, key_str(key)))
+                        .map(|f| format!(", did you mean `{}`?", join(path, f.key)))
+                        .unwrap_or_default();
+                    self.push(
+                        Severity::Warning,
+                        key.span(),
+                        format!("Unknown key `{}`{}", key_path, suggestion),
+                    );
+                }
+            }
+        }
+
+        for field in fields.iter().filter(|f| f.required) {
+            if !table.keys().any(|k| key_str(k) == field.key) {
+                self.push(
+                    Severity::Error,
+                    span.clone(),
+                    format!("Missing required key `{}`", join(path, field.key)),
+                );
+            }
+        }
+    }
+
+    fn check_value(&mut self, value: &Spanned<DeValue>, kind: &Kind, path: &str, key_span: Range<usize>) {
+        match (kind, value.get_ref()) {
+            (Kind::Integer, DeValue::Integer(_)) => {}
+            (Kind::String(format), DeValue::String(s)) => {
+                if let Some(format) = format {
+                    self.check_format(format, s, path, value.span());
+                }
+            }
+            (Kind::StringArray(allowed), DeValue::Array(items)) => {
+                for item in items.iter() {
+                    match (item.get_ref(), allowed) {
+                        (DeValue::String(s), Some(allowed)) if !allowed.contains(&s.as_ref()) => {
+                            self.push(
+                                Severity::Error,
+                                item.span(),
+                                format!(
+                                    "Unknown value \"{}\" in `{}`, expected one of {}",
+                                    s,
+                                    path,
+                                    quoted(allowed)
+                                ),
+                            )
+                        }
+                        (DeValue::String(_), _) => {}
+                        (other, _) => self.push(
+                            Severity::Error,
+                            item.span(),
+                            format!(
+                                "Items of `{}` must be strings, found {}",
+                                path,
+                                other.type_str()
+                            ),
+                        ),
+                    }
+                }
+            }
+            (Kind::StringMap, DeValue::Table(table)) => {
+                for (key, value) in table.iter() {
+                    if !value.get_ref().is_str() {
+                        self.push(
+                            Severity::Error,
+                            value.span(),
+                            format!(
+                                "`{}` must be a string, found {}",
+                                join(path, key_str(key)),
+                                value.get_ref().type_str()
+                            ),
+                        );
+                    }
+                }
+            }
+            (Kind::AnyTable, DeValue::Table(_)) => {}
+            (Kind::Table(fields), DeValue::Table(table)) => {
+                self.check_table(table, fields, path, key_span)
+            }
+            (Kind::NamedTables(fields), DeValue::Table(table)) => {
+                for (name, value) in table.iter() {
+                    let name_path = join(path, key_str(name));
+                    match value.get_ref() {
+                        DeValue::Table(inner) => {
+                            self.check_table(inner, fields, &name_path, name.span())
+                        }
+                        other => self.push(
+                            Severity::Error,
+                            value.span(),
+                            format!("`{}` must be a table, found {}", name_path, other.type_str()),
+                        ),
+                    }
+                }
+            }
+            (kind, other) => self.push(
+                Severity::Error,
+                value.span(),
+                format!("`{}` must be {}, found {}", path, kind.name(), other.type_str()),
+            ),
+        }
+    }
+
+    fn check_format(&mut self, format: &Format, value: &str, path: &str, span: Range<usize>) {
+        let regex = match format {
+            Format::BundleId => &self.bundle_id,
+            Format::BundleIdSuffix => &self.bundle_id_suffix,
+            Format::Version => &self.version,
+        };
+        if !regex.is_match(value) {
+            self.push(
+                Severity::Error,
+                span,
+                format!(
+                    "Invalid value \"{}\" for `{}`, expected {}",
+                    value,
+                    path,
+                    format.expected()
+                ),
+            );
+        }
+    }
+
+    fn push(&mut self, severity: Severity, span: Range<usize>, message: String) {
+        let (start_line, start_column) = self.position(span.start);
+        let (end_line, end_column) = self.position(span.end.max(span.start));
+        self.diagnostics.push(ConfigDiagnostic {
+            severity,
+            message,
+            start_line,
+            start_column,
+            end_line,
+            end_column,
+        });
+    }
+
+    fn position(&self, offset: usize) -> (usize, usize) {
+        let before = self.content.get(..offset).unwrap_or(self.content);
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let line = before.matches('\n').count() + 1;
+        let column = before[line_start..].encode_utf16().count() + 1;
+        (line, column)
+    }
+}
+
+fn key_str<'a>(key: &'a Spanned<DeString>) -> &'a str {
+    key.get_ref()
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn quoted(values: &[&str]) -> String {
+    values
+        .iter()
+        .map(|v| format!("\"{}\"", v))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// JSON Schema for ycode.toml, generated from the same definitions the validator uses
+pub fn json_schema() -> JsonValue {
+    let mut schema = table_schema(ROOT);
+    schema["$schema"] = json!("https://json-schema.org/draft/2020-12/schema");
+    schema["title"] = json!("ycode.toml");
+    schema["properties"]["format_version"]["maximum"] = json!(FORMAT_VERSION);
+    schema
+}
+
+fn table_schema(fields: &[Field]) -> JsonValue {
+    let mut properties = Map::new();
+    for field in fields {
+        let mut property = kind_schema(&field.kind);
+        property["description"] = json!(field.description);
+        properties.insert(field.key.to_string(), property);
+    }
+    let required: Vec<&str> = fields.iter().filter(|f| f.required).map(|f| f.key).collect();
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+fn kind_schema(kind: &Kind) -> JsonValue {
+    match kind {
+        Kind::Integer => json!({ "type": "integer" }),
+        Kind::String(None) => json!({ "type": "string" }),
+        Kind::String(Some(format)) => json!({ "type": "string", "pattern": format.pattern() }),
+        Kind::StringArray(None) => json!({ "type": "array", "items": { "type": "string" } }),
+        Kind::StringArray(Some(allowed)) => {
+            json!({ "type": "array", "items": { "type": "string", "enum": allowed } })
+        }
+        Kind::StringMap => json!({ "type": "object", "additionalProperties": { "type": "string" } }),
+        Kind::AnyTable => json!({ "type": "object" }),
+        Kind::Table(fields) => table_schema(fields),
+        Kind::NamedTables(fields) => {
+            json!({ "type": "object", "additionalProperties": table_schema(fields) })
+        }
+    }
+}
+
+#[tauri::command]
+pub fn validate_project_config(content: &str) -> Result<Vec<ConfigDiagnostic>, String> {
+    validate(content)
+}
+
+#[tauri::command]
+pub fn get_config_schema() -> JsonValue {
+    json_schema()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(project: &str) -> String {
+        format!(
+            "format_version = {}\n\n[project]\nversion_num = \"1\"\nversion_string = \"1.0.0\"\n{}",
+            FORMAT_VERSION, project
+        )
+    }
+
+    fn diagnostics(content: &str) -> Vec<ConfigDiagnostic> {
+        validate(content).unwrap()
+    }
+
+    #[test]
+    fn accepts_valid_config() {
+        let content = config("bundle_id = \"com.example.app\"\n");
+        assert!(diagnostics(&content).is_empty());
+        assert!(check(&content).is_ok());
+    }
+
+    #[test]
+    fn reports_unknown_keys_with_suggestion() {
+        let found = diagnostics(&config(
+            "bundle_id = \"com.example.app\"\nbundle_idd = \"x\"\n",
+        ));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].severity, Severity::Warning);
+        assert_eq!(
+            found[0].message,
+            "Unknown key `project.bundle_idd`, did you mean `project.bundle_id`?"
+        );
+        assert_eq!((found[0].start_line, found[0].start_column), (7, 1));
+        assert_eq!((found[0].end_line, found[0].end_column), (7, 11));
+    }
+
+    #[test]
+    fn reports_invalid_bundle_id() {
+        let found = diagnostics(&config("bundle_id = \"my app\"\n"));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].severity, Severity::Error);
+        assert!(found[0]
+            .message
+            .starts_with("Invalid value \"my app\" for `project.bundle_id`"));
+        // The span covers the string including its quotes
+        assert_eq!((found[0].start_line, found[0].start_column), (6, 13));
+        assert_eq!((found[0].end_line, found[0].end_column), (6, 21));
+    }
+
+    #[test]
+    fn reports_invalid_versions() {
+        let content = format!(
+            "format_version = {}\n\n[project]\nversion_num = \"1.x\"\nversion_string = \"1.0.0.0\"\nbundle_id = \"com.example.app\"\n",
+            FORMAT_VERSION
+        );
+        let found = diagnostics(&content);
+        let messages: Vec<&str> = found.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(found.len(), 2, "{:?}", messages);
+        assert!(messages[0].starts_with("Invalid value \"1.x\" for `project.version_num`"));
+        assert!(messages[1].starts_with("Invalid value \"1.0.0.0\" for `project.version_string`"));
+        assert_eq!(found[0].start_line, 4);
+        assert_eq!(found[1].start_line, 5);
+    }
+
+    #[test]
+    fn reports_missing_keys_and_wrong_types() {
+        let found = diagnostics(&format!(
+            "format_version = {}\n\n[project]\nversion_num = 1\n",
+            FORMAT_VERSION
+        ));
+        let messages: Vec<&str> = found.iter().map(|d| d.message.as_str()).collect();
+        assert!(messages.contains(&"`project.version_num` must be a string, found integer"));
+        assert!(messages.contains(&"Missing required key `project.bundle_id`"));
+        assert!(check(&config("")).is_err());
+    }
+
+    #[test]
+    fn counts_columns_in_utf16() {
+        let found = diagnostics(&config(
+            "bundle_id = \"com.example.app\"\ndevice_family = [\"😀\", \"tv\"]\n",
+        ));
+        assert_eq!(found.len(), 2);
+        assert_eq!(
+            found[0].message,
+            "Unknown value \"😀\" in `project.device_family`, expected one of \"iphone\", \"ipad\""
+        );
+        // The emoji is two UTF-16 code units
+        assert_eq!((found[1].start_line, found[1].start_column), (7, 24));
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        let found = diagnostics("format_version = \n");
+        assert!(!found.is_empty());
+        assert!(found.iter().all(|d| d.severity == Severity::Error));
+        assert_eq!(found[0].start_line, 1);
+    }
+
+    #[test]
+    fn checks_format_version() {
+        let older = diagnostics("format_version = 1\n\n[project]\n");
+        assert_eq!(older.len(), 1);
+        assert_eq!(older[0].severity, Severity::Warning);
+
+        let newer = diagnostics(&format!("format_version = {}\n", FORMAT_VERSION + 1));
+        assert_eq!(newer.len(), 1);
+        assert_eq!(newer[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn schema_lists_required_keys() {
+        let schema = json_schema();
+        assert_eq!(schema["required"], json!(["format_version", "project"]));
+        assert_eq!(
+            schema["properties"]["project"]["required"],
+            json!(["version_num", "version_string", "bundle_id"])
+        );
+        assert_eq!(
+            schema["properties"]["project"]["properties"]["bundle_id"]["pattern"],
+            json!(BUNDLE_ID_PATTERN)
+        );
+    }
+}
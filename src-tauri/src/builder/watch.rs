@@ -0,0 +1,238 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{mpsc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use notify_debouncer_full::{
+    new_debouncer,
+    notify::{EventKind, RecommendedWatcher, RecursiveMode},
+    DebounceEventResult, Debouncer, RecommendedCache,
+};
+use once_cell::sync::OnceCell;
+use tauri::{AppHandle, Emitter, Window};
+
+use crate::{
+    builder::{
+        hooks::{run_hook, Hook},
+        processes::cancel,
+        swift::build_swift_internal,
+    },
+    operation::Operation,
+    sideloader::{device::DeviceInfo, sideload::sideload_app},
+};
+
+// Editors often write a file in several steps, wait for them to settle before building
+const DEBOUNCE: Duration = Duration::from_millis(500);
+// Relative to the project, changes under Resources or to Info.plist only need a re-pack. Anything
+// else, like .build and .ycode which the build itself writes to, is ignored.
+const WATCHED: &[&str] = &[
+    "Sources",
+    "src",
+    "Package.swift",
+    "ycode.toml",
+    "Resources",
+    "Info.plist",
+];
+const PACK_ONLY: &[&str] = &["Resources", "Info.plist"];
+
+type Watcher = Debouncer<RecommendedWatcher, RecommendedCache>;
+
+// Active watchers, keyed by window label. Dropping one stops its build thread.
+static WATCHERS: OnceCell<Mutex<HashMap<String, Watcher>>> = OnceCell::new();
+
+fn watchers() -> &'static Mutex<HashMap<String, Watcher>> {
+    WATCHERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// Watch builds are cancelled under their own key, so they don't take other commands of the
+// window with them
+fn cancel_key(window: &Window) -> String {
+    format!("watch:{}", window.label())
+}
+
+#[derive(Clone)]
+struct WatchSettings {
+    folder: String,
+    toolchain_path: String,
+    profile: String,
+    // Set to re-deploy after every build
+    deploy: Option<(String, DeviceInfo)>,
+}
+
+// None if nothing relevant changed, Some(true) if sources changed and Some(false) if only
+// resources did
+fn classify(project_path: &Path, result: DebounceEventResult) -> Option<bool> {
+    let events = result.ok()?;
+    let mut changed = false;
+    let mut compile = false;
+    for event in events {
+        if matches!(event.kind, EventKind::Access(_) | EventKind::Other) {
+            continue;
+        }
+        for path in &event.paths {
+            let Ok(relative) = path.strip_prefix(project_path) else {
+                continue;
+            };
+            let Some(first) = relative.components().next() else {
+                continue;
+            };
+            let first = first.as_os_str().to_string_lossy();
+            if !WATCHED.contains(&first.as_ref()) {
+                continue;
+            }
+            changed = true;
+            compile |= !PACK_ONLY.contains(&first.as_ref());
+        }
+    }
+    changed.then_some(compile)
+}
+
+async fn rebuild(
+    handle: &AppHandle,
+    window: &Window,
+    settings: &WatchSettings,
+    compile: bool,
+) -> Result<(), String> {
+    let op_id = if settings.deploy.is_some() {
+        "deploy"
+    } else {
+        "build"
+    };
+    let op = Operation::new(op_id.to_string(), window).with_cancel_key(cancel_key(window));
+    let (app, config) = build_swift_internal(
+        window,
+        &op,
+        &settings.folder,
+        &settings.toolchain_path,
+        &settings.profile,
+        settings.deploy.is_none(),
+        compile,
+    )
+    .await?;
+
+    if let Some((anisette_server, device)) = &settings.deploy {
+        sideload_app(handle, window, &op, anisette_server.clone(), device.clone(), app.clone())
+            .await
+            .map_err(|e| format!("Failed to sideload app: {}", e))?;
+        run_hook(window, &op, &config, Hook::PostInstall, Some(&app)).await?;
+        window
+            .emit("build-output", "Build & Install Success")
+            .expect("failed to send output");
+    }
+    Ok(())
+}
+
+// Runs one build at a time. A change while a build is running cancels it and starts over, a
+// resource change is only packed if the last compile succeeded.
+fn run_builds(
+    handle: AppHandle,
+    window: Window,
+    settings: WatchSettings,
+    events: mpsc::Receiver<DebounceEventResult>,
+) {
+    let project_path = PathBuf::from(&settings.folder);
+    let mut current: Option<(bool, tauri::async_runtime::JoinHandle<Result<(), String>>)> = None;
+    let mut compiled = false;
+    for result in events {
+        let Some(mut compile) = classify(&project_path, result) else {
+            continue;
+        };
+
+        if let Some((was_compile, build)) = current.take() {
+            if !build.inner().is_finished() {
+                cancel(&cancel_key(&window));
+            }
+            let succeeded = matches!(tauri::async_runtime::block_on(build), Ok(Ok(())));
+            if was_compile {
+                compiled = succeeded;
+            }
+            // The cancelled compile still has to happen
+            compile |= was_compile && !succeeded;
+        }
+        compile |= !compiled;
+
+        window
+            .emit(
+                "build-output",
+                if compile {
+                    "Sources changed, rebuilding..."
+                } else {
+                    "Resources changed, repacking..."
+                },
+            )
+            .expect("failed to send output");
+        let (handle, window, settings) = (handle.clone(), window.clone(), settings.clone());
+        current = Some((
+            compile,
+            tauri::async_runtime::spawn(async move {
+                rebuild(&handle, &window, &settings, compile).await
+            }),
+        ));
+    }
+}
+
+// Rebuilds (and with `deploy` re-installs) the project whenever its sources, manifest, resources
+// or ycode.toml change, until stop_watch is called
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn watch_build(
+    handle: AppHandle,
+    window: Window,
+    folder: String,
+    toolchain_path: String,
+    profile: String,
+    deploy: bool,
+    anisette_server: Option<String>,
+    device: Option<DeviceInfo>,
+) -> Result<(), String> {
+    let deploy = match (deploy, anisette_server, device) {
+        (false, _, _) => None,
+        (true, Some(anisette_server), Some(device)) => Some((anisette_server, device)),
+        (true, _, _) => return Err("Deploying requires a device and anisette server".to_string()),
+    };
+    let project_path = PathBuf::from(&folder);
+
+    let (sender, receiver) = mpsc::channel();
+    let mut debouncer = new_debouncer(DEBOUNCE, None, sender)
+        .map_err(|e| format!("Failed to create file watcher: {}", e))?;
+    // The whole project is watched, so files replaced by a rename and directories created later
+    // are seen too. classify() picks out the relevant changes.
+    debouncer
+        .watch(&project_path, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch {}: {}", project_path.display(), e))?;
+    // Replacing an existing watcher stops it
+    watchers()
+        .lock()
+        .unwrap()
+        .insert(window.label().to_string(), debouncer);
+
+    let settings = WatchSettings {
+        folder,
+        toolchain_path,
+        profile,
+        deploy,
+    };
+    let build_window = window.clone();
+    thread::spawn(move || run_builds(handle, build_window, settings, receiver));
+
+    window
+        .emit("build-output", "Watching for changes...")
+        .expect("failed to send output");
+    Ok(())
+}
+
+// Returns false if the window had no watcher. A build that is still running is cancelled.
+#[tauri::command]
+pub fn stop_watch(window: Window) -> bool {
+    let stopped = watchers().lock().unwrap().remove(window.label()).is_some();
+    if stopped {
+        cancel(&cancel_key(&window));
+        window
+            .emit("build-output", "Stopped watching for changes")
+            .expect("failed to send output");
+    }
+    stopped
+}
@@ -0,0 +1,477 @@
+// Extracts selected files from an Xcode .xip without decompressing the rest of it to disk.
+// A .xip is a XAR archive whose "Content" file is a pbzx stream: a cpio (odc) archive split into
+// chunks that are each xz compressed, or stored as is.
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::{Component, Path, PathBuf},
+};
+
+use flate2::read::ZlibDecoder;
+use regex::Regex;
+
+use crate::builder::crossplatform::symlink;
+
+const XAR_MAGIC: &[u8] = b"xar!";
+const PBZX_MAGIC: &[u8] = b"pbzx";
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+const CPIO_MAGIC: &[u8] = b"070707";
+const CPIO_HEADER_SIZE: usize = 76;
+const CPIO_TRAILER: &str = "TRAILER!!!";
+// Where hard linked files that weren't wanted are kept, in case a later link to them is
+const LINKS_DIR: &str = ".xip-links";
+
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+
+// Writes the entries `wanted` accepts to `output`, keeping their paths (e.g. Xcode.app/Contents/..).
+// `on_progress` gets the compressed bytes read so far and the total.
+pub fn extract_entries(
+    xip_path: &Path,
+    output: &Path,
+    wanted: impl Fn(&Path) -> bool,
+    mut on_progress: impl FnMut(u64, u64),
+) -> Result<(), String> {
+    let mut file = File::open(xip_path)
+        .map_err(|e| format!("Failed to open {}: {}", xip_path.display(), e))?;
+    let (offset, length) = content_range(&mut file)?;
+    file.seek(SeekFrom::Start(offset))
+        .map_err(|e| format!("Failed to read xip: {}", e))?;
+
+    let content = BufReader::new(file).take(length);
+    let pbzx = PbzxReader::new(content, |read| on_progress(read, length))?;
+    extract_cpio(BufReader::new(pbzx), output, wanted)
+}
+
+// Offset and length of the Content file, from the XAR table of contents
+fn content_range(file: &mut (impl Read + Seek)) -> Result<(u64, u64), String> {
+    let mut header = [0u8; 28];
+    file.read_exact(&mut header)
+        .map_err(|e| format!("Failed to read xip header: {}", e))?;
+    if &header[0..4] != XAR_MAGIC {
+        return Err("Not a xip file".to_string());
+    }
+    let header_size = u16::from_be_bytes([header[4], header[5]]) as u64;
+    let toc_length = u64::from_be_bytes(header[8..16].try_into().unwrap());
+
+    file.seek(SeekFrom::Start(header_size))
+        .map_err(|e| format!("Failed to read xip: {}", e))?;
+    let mut toc = String::new();
+    ZlibDecoder::new(file.take(toc_length))
+        .read_to_string(&mut toc)
+        .map_err(|e| format!("Failed to read xip table of contents: {}", e))?;
+    // The heap, which the data offsets are relative to, follows the table of contents
+    let heap = header_size + toc_length;
+
+    let regex = |pattern: &str| Regex::new(pattern).map_err(|e| format!("Invalid regex: {}", e));
+    let file_regex = regex(r"(?s)<file\b.*?</file>")?;
+    // Extended attributes carry their own offset and length, only the file's <data> is read
+    let data_regex = regex(r"(?s)<data>.*?</data>")?;
+    let offset_regex = regex(r"<offset>(\d+)</offset>")?;
+    let length_regex = regex(r"<length>(\d+)</length>")?;
+    let encoding_regex = regex(r#"<encoding\s+style="([^"]*)""#)?;
+
+    let content = file_regex
+        .find_iter(&toc)
+        .map(|m| m.as_str())
+        .find(|f| f.contains("<name>Content</name>"))
+        .ok_or("xip has no Content".to_string())?;
+    let data = data_regex
+        .find(content)
+        .map(|m| m.as_str())
+        .ok_or("Invalid xip table of contents".to_string())?;
+    if let Some(encoding) = encoding_regex.captures(data) {
+        if &encoding[1] != "application/octet-stream" {
+            return Err(format!(
+                "Unsupported xip content encoding: {}",
+                &encoding[1]
+            ));
+        }
+    }
+    let number = |regex: &Regex| {
+        regex
+            .captures(data)
+            .and_then(|c| c[1].parse::<u64>().ok())
+            .ok_or("Invalid xip table of contents".to_string())
+    };
+    Ok((heap + number(&offset_regex)?, number(&length_regex)?))
+}
+
+// Decompresses a pbzx stream one chunk at a time
+struct PbzxReader<R: Read, F: FnMut(u64)> {
+    inner: R,
+    chunk: Vec<u8>,
+    position: usize,
+    // Compressed bytes read, for progress
+    read: u64,
+    on_chunk: F,
+}
+
+impl<R: Read, F: FnMut(u64)> PbzxReader<R, F> {
+    fn new(mut inner: R, on_chunk: F) -> Result<Self, String> {
+        // Magic followed by the uncompressed chunk size
+        let mut header = [0u8; 12];
+        inner
+            .read_exact(&mut header)
+            .map_err(|e| format!("Failed to read xip content: {}", e))?;
+        if &header[0..4] != PBZX_MAGIC {
+            return Err("xip content is not a pbzx stream".to_string());
+        }
+        Ok(PbzxReader {
+            inner,
+            chunk: Vec::new(),
+            position: 0,
+            read: 12,
+            on_chunk,
+        })
+    }
+
+    // Returns false at the end of the stream
+    fn next_chunk(&mut self) -> io::Result<bool> {
+        let mut header = [0u8; 16];
+        let mut filled = 0;
+        while filled < header.len() {
+            match self.inner.read(&mut header[filled..])? {
+                0 if filled == 0 => return Ok(false),
+                0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+                n => filled += n,
+            }
+        }
+        let size = u64::from_be_bytes(header[8..16].try_into().unwrap());
+        let mut data = Vec::new();
+        (&mut self.inner).take(size).read_to_end(&mut data)?;
+        if (data.len() as u64) < size {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        self.chunk.clear();
+        if data.starts_with(XZ_MAGIC) {
+            liblzma::read::XzDecoder::new(&data[..]).read_to_end(&mut self.chunk)?;
+        } else {
+            self.chunk = data;
+        }
+        self.position = 0;
+        self.read += 16 + size;
+        (self.on_chunk)(self.read);
+        Ok(true)
+    }
+}
+
+impl<R: Read, F: FnMut(u64)> Read for PbzxReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.chunk.len() {
+            if !self.next_chunk()? {
+                return Ok(0);
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.position);
+        buf[..n].copy_from_slice(&self.chunk[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+fn octal(field: &[u8]) -> Result<u64, String> {
+    std::str::from_utf8(field)
+        .ok()
+        .and_then(|s| u64::from_str_radix(s, 8).ok())
+        .ok_or("Invalid cpio header".to_string())
+}
+
+fn extract_cpio(
+    mut reader: impl Read,
+    output: &Path,
+    wanted: impl Fn(&Path) -> bool,
+) -> Result<(), String> {
+    let read_err = |e: io::Error| format!("Failed to read xip content: {}", e);
+    // Hard linked files only carry their data once, keyed by (dev, ino)
+    let mut links: HashMap<(u64, u64), PathBuf> = HashMap::new();
+    let links_dir = output.join(LINKS_DIR);
+    loop {
+        let mut header = [0u8; CPIO_HEADER_SIZE];
+        reader.read_exact(&mut header).map_err(read_err)?;
+        if &header[0..6] != CPIO_MAGIC {
+            return Err("Invalid cpio header".to_string());
+        }
+        let dev = octal(&header[6..12])?;
+        let ino = octal(&header[12..18])?;
+        let mode = octal(&header[18..24])? as u32;
+        let nlink = octal(&header[36..42])?;
+        let name_size = octal(&header[59..65])?;
+        let file_size = octal(&header[65..76])?;
+
+        let mut name = vec![0u8; name_size as usize];
+        reader.read_exact(&mut name).map_err(read_err)?;
+        let name = String::from_utf8_lossy(name.strip_suffix(&[0]).unwrap_or(&name)).to_string();
+        if name == CPIO_TRAILER {
+            if links_dir.exists() {
+                fs::remove_dir_all(&links_dir)
+                    .map_err(|e| format!("Failed to remove {}: {}", links_dir.display(), e))?;
+            }
+            return Ok(());
+        }
+
+        let path = Path::new(&name);
+        let key = (dev, ino);
+        if !wanted(path) {
+            if mode & S_IFMT == S_IFREG && nlink > 1 && !links.contains_key(&key) {
+                let dest = links_dir.join(format!("{}-{}", dev, ino));
+                fs::create_dir_all(&links_dir)
+                    .map_err(|e| format!("Failed to create dir: {}", e))?;
+                write_file(&mut reader, &dest, file_size)?;
+                links.insert(key, dest);
+            } else {
+                io::copy(&mut (&mut reader).take(file_size), &mut io::sink()).map_err(read_err)?;
+            }
+            continue;
+        }
+        let dest = output.join(relative_path(path)?);
+        let create_parent = |dest: &Path| match dest.parent() {
+            Some(parent) => fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create parent dir: {}", e)),
+            None => Ok(()),
+        };
+
+        match mode & S_IFMT {
+            S_IFDIR => {
+                fs::create_dir_all(&dest).map_err(|e| format!("Failed to create dir: {}", e))?;
+            }
+            S_IFLNK => {
+                let mut target = String::new();
+                (&mut reader)
+                    .take(file_size)
+                    .read_to_string(&mut target)
+                    .map_err(read_err)?;
+                create_parent(&dest)?;
+                symlink(&target, &dest.to_string_lossy())
+                    .map_err(|e| format!("Failed to create symlink: {}", e))?;
+            }
+            S_IFREG => {
+                create_parent(&dest)?;
+                match links.get(&key) {
+                    Some(first) if file_size == 0 => {
+                        fs::copy(first, &dest)
+                            .map_err(|e| format!("Failed to copy file: {}", e))?;
+                    }
+                    _ => {
+                        write_file(&mut reader, &dest, file_size)?;
+                        if nlink > 1 {
+                            links.insert(key, dest.clone());
+                        }
+                    }
+                }
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    fs::set_permissions(&dest, fs::Permissions::from_mode(mode & 0o7777))
+                        .map_err(|e| format!("Failed to set permissions: {}", e))?;
+                }
+            }
+            _ => {
+                io::copy(&mut (&mut reader).take(file_size), &mut io::sink()).map_err(read_err)?;
+            }
+        }
+    }
+}
+
+fn write_file(reader: &mut impl Read, dest: &Path, size: u64) -> Result<(), String> {
+    let mut file =
+        File::create(dest).map_err(|e| format!("Failed to create {}: {}", dest.display(), e))?;
+    io::copy(&mut reader.take(size), &mut file)
+        .map_err(|e| format!("Failed to write {}: {}", dest.display(), e))?;
+    Ok(())
+}
+
+// Archive paths start with "./", anything leaving the output directory is refused
+fn relative_path(path: &Path) -> Result<PathBuf, String> {
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir => {}
+            _ => return Err(format!("Invalid path in xip: {}", path.display())),
+        }
+    }
+    Ok(relative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+
+    fn entry(name: &str, mode: u32, ino: u64, nlink: u64, data: &[u8]) -> Vec<u8> {
+        let mut header = format!(
+            "070707{:06o}{:06o}{:06o}{:06o}{:06o}{:06o}{:06o}{:011o}{:06o}{:011o}",
+            1,
+            ino,
+            mode,
+            0,
+            0,
+            nlink,
+            0,
+            0,
+            name.len() + 1,
+            data.len()
+        )
+        .into_bytes();
+        header.extend_from_slice(name.as_bytes());
+        header.push(0);
+        header.extend_from_slice(data);
+        header
+    }
+
+    fn cpio(entries: &[Vec<u8>]) -> Vec<u8> {
+        let mut archive = entries.concat();
+        archive.extend(entry(CPIO_TRAILER, 0, 0, 1, b""));
+        archive
+    }
+
+    // Each chunk is (data, compressed)
+    fn pbzx(chunks: &[(&[u8], bool)]) -> Vec<u8> {
+        let mut stream = PBZX_MAGIC.to_vec();
+        stream.extend(16u64.to_be_bytes());
+        for (data, compressed) in chunks {
+            stream.extend((data.len() as u64).to_be_bytes());
+            let data = if *compressed {
+                let mut xz = Vec::new();
+                liblzma::read::XzEncoder::new(*data, 6)
+                    .read_to_end(&mut xz)
+                    .unwrap();
+                xz
+            } else {
+                data.to_vec()
+            };
+            stream.extend((data.len() as u64).to_be_bytes());
+            stream.extend(data);
+        }
+        stream
+    }
+
+    fn output_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ycode-xip-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn reads_stored_and_xz_chunks() {
+        let archive = cpio(&[
+            entry("./a.txt", S_IFREG | 0o644, 2, 1, &[b'a'; 300]),
+            entry("./b.txt", S_IFREG | 0o755, 3, 1, b"hello b"),
+        ]);
+        let (first, second) = archive.split_at(200);
+        let stream = pbzx(&[(first, true), (second, false)]);
+        let output = output_dir("chunks");
+
+        let pbzx = PbzxReader::new(&stream[..], |_| {}).unwrap();
+        extract_cpio(BufReader::new(pbzx), &output, |_| true).unwrap();
+
+        assert_eq!(fs::read(output.join("a.txt")).unwrap(), vec![b'a'; 300]);
+        assert_eq!(fs::read(output.join("b.txt")).unwrap(), b"hello b");
+        fs::remove_dir_all(output).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn creates_symlinks() {
+        let archive = cpio(&[
+            entry("./dir", S_IFDIR | 0o755, 2, 2, b""),
+            entry("./dir/target", S_IFREG | 0o644, 3, 1, b"target"),
+            entry("./dir/link", S_IFLNK | 0o755, 4, 1, b"target"),
+        ]);
+        let output = output_dir("symlink");
+
+        extract_cpio(&archive[..], &output, |_| true).unwrap();
+
+        let link = output.join("dir").join("link");
+        assert_eq!(fs::read_link(&link).unwrap(), Path::new("target"));
+        assert_eq!(fs::read(link).unwrap(), b"target");
+        fs::remove_dir_all(output).unwrap();
+    }
+
+    #[test]
+    fn copies_hard_links_to_unwanted_files() {
+        let archive = cpio(&[
+            entry("./skip/first", S_IFREG | 0o755, 2, 2, b"linked"),
+            entry("./keep/second", S_IFREG | 0o755, 2, 2, b""),
+        ]);
+        let output = output_dir("hardlink");
+
+        extract_cpio(&archive[..], &output, |path| !path.starts_with("./skip")).unwrap();
+
+        assert_eq!(
+            fs::read(output.join("keep").join("second")).unwrap(),
+            b"linked"
+        );
+        assert!(!output.join("skip").exists());
+        assert!(!output.join(LINKS_DIR).exists());
+        fs::remove_dir_all(output).unwrap();
+    }
+
+    #[test]
+    fn stops_at_trailer() {
+        let mut archive = cpio(&[entry("./a.txt", S_IFREG | 0o644, 2, 1, b"a")]);
+        archive.extend(entry("./after.txt", S_IFREG | 0o644, 3, 1, b"after"));
+        let output = output_dir("trailer");
+
+        extract_cpio(&archive[..], &output, |_| true).unwrap();
+
+        assert!(output.join("a.txt").exists());
+        assert!(!output.join("after.txt").exists());
+        fs::remove_dir_all(output).unwrap();
+    }
+
+    #[test]
+    fn fails_on_truncated_input() {
+        let archive = cpio(&[entry("./a.txt", S_IFREG | 0o644, 2, 1, &[b'a'; 100])]);
+        let output = output_dir("truncated");
+
+        assert!(extract_cpio(&archive[..120], &output, |_| true).is_err());
+        let stream = pbzx(&[(&archive, true)]);
+        assert!(PbzxReader::new(&stream[..stream.len() - 10], |_| {})
+            .unwrap()
+            .read_to_end(&mut Vec::new())
+            .is_err());
+        fs::remove_dir_all(output).unwrap();
+    }
+
+    #[test]
+    fn rejects_paths_outside_output() {
+        let archive = cpio(&[entry("../escaped.txt", S_IFREG | 0o644, 2, 1, b"x")]);
+        let output = output_dir("escape").join("output");
+        fs::create_dir_all(&output).unwrap();
+
+        assert!(extract_cpio(&archive[..], &output, |_| true).is_err());
+        assert!(!output.parent().unwrap().join("escaped.txt").exists());
+        fs::remove_dir_all(output.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn reads_content_range_from_data() {
+        let toc = r#"<xar><toc>
+<file id="1"><data><length>5</length><offset>0</offset><size>5</size><encoding style="application/octet-stream"/></data><name>Metadata</name></file>
+<file id="2"><ea><name>com.apple.attr</name><offset>5</offset><length>3</length><size>3</size><encoding style="application/x-gzip"/></ea><data><length>100</length><offset>8</offset><size>100</size><encoding style="application/octet-stream"/></data><name>Content</name></file>
+</toc></xar>"#;
+        let mut compressed = flate2::write::ZlibEncoder::new(Vec::new(), Default::default());
+        compressed.write_all(toc.as_bytes()).unwrap();
+        let compressed = compressed.finish().unwrap();
+        let mut xip = XAR_MAGIC.to_vec();
+        xip.extend(28u16.to_be_bytes());
+        xip.extend(1u16.to_be_bytes());
+        xip.extend((compressed.len() as u64).to_be_bytes());
+        xip.extend((toc.len() as u64).to_be_bytes());
+        xip.extend(1u32.to_be_bytes());
+        xip.extend(&compressed);
+
+        let heap = 28 + compressed.len() as u64;
+        assert_eq!(
+            content_range(&mut Cursor::new(xip)).unwrap(),
+            (heap + 8, 100)
+        );
+    }
+}
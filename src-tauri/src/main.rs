@@ -21,11 +21,21 @@ use sideloader::{
 use tauri::Emitter;
 use templates::create_template;
 
+use builder::dependencies::{
+    add_package_dependency, remove_package_dependency, resolve_packages, show_dependencies,
+    update_packages,
+};
+use builder::manifest::get_package_model;
+use builder::processes::cancel_command;
 use builder::sdk::install_sdk_operation;
 use builder::swift::{
     build_swift, clean_swift, deploy_swift, get_swiftly_toolchains, get_toolchain_info,
-    has_darwin_sdk, validate_toolchain,
+    has_darwin_sdk, init_project, validate_toolchain,
 };
+use builder::tasks::{list_tasks, run_task};
+use builder::testing::test_swift;
+use builder::validate::{get_config_schema, validate_project_config};
+use builder::watch::{stop_watch, watch_build};
 use windows::{has_wsl, is_windows};
 
 fn main() {
@@ -41,6 +51,19 @@ fn main() {
             build_swift,
             deploy_swift,
             clean_swift,
+            init_project,
+            cancel_command,
+            test_swift,
+            watch_build,
+            stop_watch,
+            list_tasks,
+            run_task,
+            resolve_packages,
+            update_packages,
+            show_dependencies,
+            add_package_dependency,
+            remove_package_dependency,
+            get_package_model,
             refresh_idevice,
             delete_stored_credentials,
             reset_anisette,
@@ -55,6 +78,8 @@ fn main() {
             get_toolchain_info,
             install_sdk_operation,
             has_darwin_sdk,
+            validate_project_config,
+            get_config_schema,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,9 +1,19 @@
 use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 use tauri::{Emitter, Window};
 
-pub struct Operation<'a> {
+#[derive(Clone)]
+pub struct Operation {
     id: String,
-    window: &'a Window,
+    window: Window,
+    // When each step was started, to report how long it took
+    started: Arc<Mutex<HashMap<String, Instant>>>,
+    // Key the operation's commands are registered under in builder::processes
+    cancel_key: String,
 }
 
 #[derive(Clone, Serialize)]
@@ -12,11 +22,35 @@ struct OperationUpdate<'a> {
     update_type: &'a str,
     step_id: &'a str,
     extra_details: Option<String>,
+    duration_ms: Option<u64>,
+    progress: Option<OperationProgress>,
 }
 
-impl<'a> Operation<'a> {
-    pub fn new(id: String, window: &'a Window) -> Operation<'a> {
-        Operation { id, window }
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OperationProgress {
+    completed: u64,
+    total: u64,
+}
+
+impl Operation {
+    pub fn new(id: String, window: &Window) -> Operation {
+        Operation {
+            id,
+            window: window.clone(),
+            started: Arc::new(Mutex::new(HashMap::new())),
+            cancel_key: window.label().to_string(),
+        }
+    }
+
+    // By default the operation's commands are cancelled with the rest of the window's
+    pub fn with_cancel_key(mut self, key: String) -> Operation {
+        self.cancel_key = key;
+        self
+    }
+
+    pub fn cancel_key(&self) -> &str {
+        &self.cancel_key
     }
 
     pub fn move_on(&self, old_id: &str, new_id: &str) -> Result<(), String> {
@@ -25,42 +59,47 @@ impl<'a> Operation<'a> {
     }
 
     pub fn start(&self, id: &str) -> Result<(), String> {
-        self.window
-            .emit(
-                &format!("operation_{}", self.id),
-                OperationUpdate {
-                    update_type: "started",
-                    step_id: id,
-                    extra_details: None,
-                },
-            )
-            .map_err(|_| "Failed to emit status to frontend".to_string())
+        self.started
+            .lock()
+            .unwrap()
+            .insert(id.to_string(), Instant::now());
+        self.emit(OperationUpdate {
+            update_type: "started",
+            step_id: id,
+            extra_details: None,
+            duration_ms: None,
+            progress: None,
+        })
+    }
+
+    pub fn progress(&self, id: &str, completed: u64, total: u64) -> Result<(), String> {
+        self.emit(OperationUpdate {
+            update_type: "progress",
+            step_id: id,
+            extra_details: None,
+            duration_ms: None,
+            progress: Some(OperationProgress { completed, total }),
+        })
     }
 
     pub fn complete(&self, id: &str) -> Result<(), String> {
-        self.window
-            .emit(
-                &format!("operation_{}", self.id),
-                OperationUpdate {
-                    update_type: "finished",
-                    step_id: id,
-                    extra_details: None,
-                },
-            )
-            .map_err(|_| "Failed to emit status to frontend".to_string())
+        self.emit(OperationUpdate {
+            update_type: "finished",
+            step_id: id,
+            extra_details: None,
+            duration_ms: self.duration_ms(id),
+            progress: None,
+        })
     }
 
     pub fn fail<T>(&self, id: &str, error: String) -> Result<T, String> {
-        self.window
-            .emit(
-                &format!("operation_{}", self.id),
-                OperationUpdate {
-                    update_type: "failed",
-                    step_id: id,
-                    extra_details: Some(error.clone()),
-                },
-            )
-            .map_err(|_| "Failed to emit status to frontend".to_string())?;
+        self.emit(OperationUpdate {
+            update_type: "failed",
+            step_id: id,
+            extra_details: Some(error.clone()),
+            duration_ms: self.duration_ms(id),
+            progress: None,
+        })?;
         return Err(error);
     }
 
@@ -85,4 +124,18 @@ impl<'a> Operation<'a> {
             }
         }
     }
+
+    fn duration_ms(&self, id: &str) -> Option<u64> {
+        self.started
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|started| started.elapsed().as_millis() as u64)
+    }
+
+    fn emit(&self, update: OperationUpdate) -> Result<(), String> {
+        self.window
+            .emit(&format!("operation_{}", self.id), update)
+            .map_err(|_| "Failed to emit status to frontend".to_string())
+    }
 }
@@ -1,16 +1,30 @@
 use std::{path::PathBuf, sync::Arc};
 
-use crate::sideloader::device::{list_devices, DeviceInfo};
+use crate::{
+    operation::Operation,
+    sideloader::device::{list_devices, DeviceInfo},
+};
+use futures::{
+    channel::mpsc::{self, UnboundedSender},
+    future::{select, Either},
+    pin_mut, StreamExt,
+};
 use idevice::usbmuxd::{UsbmuxdAddr, UsbmuxdConnection};
 use isideload::{sideload, Error, SideloadConfiguration, SideloadLogger};
 use tauri::{Emitter, Manager, Window};
 
+// isideload starts every message about copying the app to the device and installing it with this
+const INSTALL_MESSAGE: &str = "Installing app";
+
 pub struct TauriLogger {
     window: Arc<Window>,
+    // Also receives every message, see sideload_app
+    messages: UnboundedSender<String>,
 }
 
 impl SideloadLogger for TauriLogger {
     fn log(&self, message: &str) {
+        self.messages.unbounded_send(message.to_string()).ok();
         self.window.emit("build-output", message.to_string()).ok();
     }
 
@@ -24,24 +38,31 @@ impl SideloadLogger for TauriLogger {
 pub async fn sideload_app(
     handle: &tauri::AppHandle,
     window: &tauri::Window,
+    op: &Operation,
     anisette_server: String,
     device: DeviceInfo,
     app_path: PathBuf,
 ) -> Result<(), String> {
-    let dev_session =
+    op.start("authenticate")?;
+    let dev_session = op.fail_if_err(
+        "authenticate",
         crate::sideloader::apple::get_developer_session(&handle, &window, anisette_server.clone())
-            .await?;
+            .await,
+    )?;
+    op.move_on("authenticate", "sign")?;
+
+    let (sender, mut messages) = mpsc::unbounded();
     let logger = TauriLogger {
         window: Arc::new(window.clone()),
+        messages: sender,
     };
-    let store_dir = handle.path().app_config_dir().map_err(|e| e.to_string())?;
-    let mut usbmuxd = UsbmuxdConnection::default()
-        .await
-        .map_err(|e| format!("Failed to connect to usbmuxd: {}", e))?;
-    let device = usbmuxd
-        .get_device(&device.uuid)
-        .await
-        .map_err(|e| format!("Failed to get device: {}", e))?;
+    let store_dir = op.fail_if_err_map("sign", handle.path().app_config_dir(), |e| e.to_string())?;
+    let mut usbmuxd = op.fail_if_err_map("sign", UsbmuxdConnection::default().await, |e| {
+        format!("Failed to connect to usbmuxd: {}", e)
+    })?;
+    let device = op.fail_if_err_map("sign", usbmuxd.get_device(&device.uuid).await, |e| {
+        format!("Failed to get device: {}", e)
+    })?;
 
     let config = SideloadConfiguration::new()
         .set_store_dir(store_dir.clone())
@@ -49,11 +70,39 @@ pub async fn sideload_app(
         .set_machine_name("YCode".to_string());
 
     let provider = device.to_provider(UsbmuxdAddr::from_env_var().unwrap(), "y-code");
-    sideload::sideload_app(&provider, &dev_session, app_path, config)
-        .await
-        .map_err(|e| e.to_string())?;
+    // isideload signs and installs in one call, so its messages are followed to move on to the
+    // install step once it starts installing
+    let sideload = sideload::sideload_app(&provider, &dev_session, app_path, config);
+    pin_mut!(sideload);
+    let mut installing = false;
+    let mut follow = |message: String| {
+        if !installing && message.starts_with(INSTALL_MESSAGE) {
+            installing = true;
+            op.move_on("sign", "install").ok();
+        }
+    };
+    let result = loop {
+        match select(messages.next(), sideload.as_mut()).await {
+            Either::Left((Some(message), _)) => follow(message),
+            Either::Left((None, sideload)) => break sideload.await,
+            Either::Right((result, _)) => break result,
+        }
+    }
+    .map_err(|e| e.to_string());
+    // Messages sent while it was polled for the last time
+    while let Ok(message) = messages.try_recv() {
+        follow(message);
+    }
 
-    Ok(())
+    match result {
+        Ok(()) => {
+            if !installing {
+                op.move_on("sign", "install")?;
+            }
+            op.complete("install")
+        }
+        Err(e) => op.fail(if installing { "install" } else { "sign" }, e),
+    }
 }
 
 #[tauri::command]
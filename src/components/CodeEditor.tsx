@@ -11,6 +11,7 @@ import "./CodeEditor.css";
 import { useColorScheme } from "@mui/joy/styles";
 import { path } from "@tauri-apps/api";
 import * as fs from "@tauri-apps/plugin-fs";
+import { invoke } from "@tauri-apps/api/core";
 
 import { initialize } from "@codingame/monaco-vscode-api";
 import getLanguagesServiceOverride from "@codingame/monaco-vscode-languages-service-override";
@@ -62,6 +63,39 @@ export interface CodeEditorHandles {
   saveFile: () => void;
 }
 
+interface ConfigDiagnostic {
+  severity: "error" | "warning";
+  message: string;
+  startLine: number;
+  startColumn: number;
+  endLine: number;
+  endColumn: number;
+}
+
+// Shows validate_project_config's diagnostics for ycode.toml as markers
+const validateConfig = async (model: monaco.editor.ITextModel) => {
+  const diagnostics = await invoke<ConfigDiagnostic[]>(
+    "validate_project_config",
+    { content: model.getValue() }
+  );
+  if (model.isDisposed()) return;
+  monaco.editor.setModelMarkers(
+    model,
+    "ycode.toml",
+    diagnostics.map((d) => ({
+      severity:
+        d.severity === "error"
+          ? monaco.MarkerSeverity.Error
+          : monaco.MarkerSeverity.Warning,
+      message: d.message,
+      startLineNumber: d.startLine,
+      startColumn: d.startColumn,
+      endLineNumber: d.endLine,
+      endColumn: d.endColumn,
+    }))
+  );
+};
+
 const getLanguage = async (filename: string) => {
   if (filename === "Makefile") {
     return "make";
@@ -217,6 +251,38 @@ const CodeEditor = forwardRef<CodeEditorHandles, CodeEditorProps>(
       }
     }, [editor, setUnsaved, originalText]);
 
+    useEffect(() => {
+      if (!editor || !originalText) return;
+      const model = editor.getModel();
+      if (model === null) return;
+      let cancelled = false;
+      let cleanup = () => {};
+      path.basename(file).then((name) => {
+        if (cancelled) return;
+        if (name !== "ycode.toml") {
+          monaco.editor.setModelMarkers(model, "ycode.toml", []);
+          return;
+        }
+        validateConfig(model).catch(console.error);
+        let timeout: ReturnType<typeof setTimeout> | undefined;
+        const listener = model.onDidChangeContent(() => {
+          clearTimeout(timeout);
+          timeout = setTimeout(
+            () => validateConfig(model).catch(console.error),
+            300
+          );
+        });
+        cleanup = () => {
+          clearTimeout(timeout);
+          listener.dispose();
+        };
+      });
+      return () => {
+        cancelled = true;
+        cleanup();
+      };
+    }, [editor, file, originalText]);
+
     if (failedReason !== null) {
       return <div className={"editor-failed"}>{failedReason}</div>;
     }
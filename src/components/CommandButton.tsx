@@ -1,6 +1,7 @@
 import { Button, MenuItem } from "@mui/joy";
 import { useCommandRunner } from "../utilities/Command";
 import { useIDE } from "../utilities/IDEContext";
+import { Operation } from "../utilities/operations";
 
 export interface CommandButtonProps {
   command: string;
@@ -16,6 +17,8 @@ export interface CommandButtonProps {
   useMenuItem?: boolean;
   shortcut?: React.ReactNode;
   id?: string;
+  // Steps reported by the command, shown while it runs
+  operation?: Operation;
 }
 
 export default function CommandButton({
@@ -32,10 +35,11 @@ export default function CommandButton({
   useMenuItem = false,
   shortcut,
   id,
+  operation,
 }: CommandButtonProps) {
   const { isRunningCommand, currentCommand, runCommand, cancelCommand } =
     useCommandRunner();
-  const { setConsoleLines } = useIDE();
+  const { setConsoleLines, trackOperation } = useIDE();
 
   const Component: React.ElementType = useMenuItem ? MenuItem : Button;
 
@@ -57,7 +61,7 @@ export default function CommandButton({
             }
       }
       title={tooltip}
-      onClick={() => {
+      onClick={async () => {
         if (!validate()) {
           return;
         }
@@ -70,7 +74,16 @@ export default function CommandButton({
           }
           return;
         }
-        runCommand(command, parameters);
+        if (!operation) {
+          runCommand(command, parameters);
+          return;
+        }
+        const unlisten = await trackOperation(operation);
+        try {
+          await runCommand(command, parameters);
+        } finally {
+          unlisten();
+        }
       }}
       id={id}
     >
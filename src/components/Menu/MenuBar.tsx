@@ -18,6 +18,7 @@ import { DeviceInfo, useIDE } from "../../utilities/IDEContext";
 import { useStore } from "../../utilities/StoreContext";
 import { useToast } from "react-toast-plus";
 import bar from "./MenuBarDefinition";
+import { buildOperation, deployOperation } from "../../utilities/operations";
 
 export interface MenuBarProps {
   callbacks: Record<string, () => void>;
@@ -230,9 +231,10 @@ export default function MenuBar({ callbacks }: MenuBarProps) {
         parameters={{
           folder: path,
           toolchainPath: selectedToolchain?.path ?? "",
-          debug: true,
+          profile: "debug",
         }}
         tooltip="Build .ipa"
+        operation={buildOperation}
         sx={{ marginRight: 0 }}
       />
       <Divider orientation="vertical" />
@@ -273,13 +275,14 @@ export default function MenuBar({ callbacks }: MenuBarProps) {
           tooltip="Build & Install"
           variant="plain"
           command="deploy_swift"
+          operation={deployOperation}
           icon={<PhonelinkSetup />}
           parameters={{
             folder: path,
             anisetteServer,
             device: selectedDevice,
             toolchainPath: selectedToolchain?.path ?? "",
-            debug: true,
+            profile: "debug",
           }}
           validate={() => {
             if (!selectedDevice) {
@@ -6,6 +6,7 @@ import { useIDE } from "../../utilities/IDEContext";
 import CommandButton from "../CommandButton";
 import { useStore } from "../../utilities/StoreContext";
 import { useToast } from "react-toast-plus";
+import { buildOperation, deployOperation } from "../../utilities/operations";
 
 export default [
   {
@@ -25,6 +26,26 @@ export default [
             name: "New Project...",
             callbackName: "newProject",
           },
+          {
+            name: "Initialize YCode Project",
+            component: () => {
+              const { path } = useParams<"path">();
+              const { selectedToolchain } = useIDE();
+              return (
+                <CommandButton
+                  command="init_project"
+                  parameters={{
+                    folder: path,
+                    toolchainPath: selectedToolchain?.path ?? "",
+                  }}
+                  label="Initialize YCode Project"
+                  useMenuItem
+                  id="initProjectMenuBtn"
+                />
+              );
+            },
+            componentId: "initProjectMenuBtn",
+          },
         ],
       },
       {
@@ -162,9 +183,10 @@ export default [
                   parameters={{
                     folder: path,
                     toolchainPath: selectedToolchain?.path ?? "",
-                    debug: true,
+                    profile: "debug",
                   }}
                   label="Build .ipa (Debug)"
+                  operation={buildOperation}
                   useMenuItem
                   id="buildDebugMenuBtn"
                 />
@@ -185,9 +207,10 @@ export default [
                   parameters={{
                     folder: path,
                     toolchainPath: selectedToolchain?.path ?? "",
-                    debug: false,
+                    profile: "release",
                   }}
                   label="Build .ipa (Release)"
+                  operation={buildOperation}
                   useMenuItem
                   id="buildReleaseMenuBtn"
                 />
@@ -215,9 +238,10 @@ export default [
                     anisetteServer,
                     device: selectedDevice,
                     toolchainPath: selectedToolchain?.path ?? "",
-                    debug: true,
+                    profile: "debug",
                   }}
                   label="Build & Install"
+                  operation={deployOperation}
                   validate={() => {
                     if (!selectedDevice) {
                       addToast.error("Please select a device to deploy to.");
@@ -234,6 +258,82 @@ export default [
           },
         ],
       },
+      {
+        label: "Watch",
+        items: [
+          {
+            name: "Watch & Rebuild",
+            component: () => {
+              const { path } = useParams<"path">();
+              const { selectedToolchain } = useIDE();
+              return (
+                <CommandButton
+                  command="watch_build"
+                  parameters={{
+                    folder: path,
+                    toolchainPath: selectedToolchain?.path ?? "",
+                    profile: "debug",
+                    deploy: false,
+                  }}
+                  label="Watch & Rebuild"
+                  useMenuItem
+                  id="watchMenuBtn"
+                />
+              );
+            },
+            componentId: "watchMenuBtn",
+          },
+          {
+            name: "Watch & Install",
+            component: ({ selectedDevice }) => {
+              const { path } = useParams<"path">();
+              const { selectedToolchain } = useIDE();
+              const [anisetteServer] = useStore<string>(
+                "apple-id/anisette-server",
+                "ani.sidestore.io"
+              );
+              const { addToast } = useToast();
+              return (
+                <CommandButton
+                  command="watch_build"
+                  parameters={{
+                    folder: path,
+                    toolchainPath: selectedToolchain?.path ?? "",
+                    profile: "debug",
+                    deploy: true,
+                    anisetteServer,
+                    device: selectedDevice,
+                  }}
+                  label="Watch & Install"
+                  validate={() => {
+                    if (!selectedDevice) {
+                      addToast.error("Please select a device to deploy to.");
+                      return false;
+                    }
+                    return true;
+                  }}
+                  useMenuItem
+                  id="watchDeployMenuBtn"
+                />
+              );
+            },
+            componentId: "watchDeployMenuBtn",
+          },
+          {
+            name: "Stop Watching",
+            component: () => (
+              <CommandButton
+                command="stop_watch"
+                label="Stop Watching"
+                clearConsole={false}
+                useMenuItem
+                id="stopWatchMenuBtn"
+              />
+            ),
+            componentId: "stopWatchMenuBtn",
+          },
+        ],
+      },
       {
         label: "Clean",
         items: [
@@ -261,6 +361,128 @@ export default [
           },
         ],
       },
+      {
+        label: "Test",
+        items: [
+          {
+            name: "Run Tests",
+            shortcut: "Ctrl+U",
+            component: ({ shortcut }) => {
+              const { path } = useParams<"path">();
+              const { selectedToolchain } = useIDE();
+              return (
+                <CommandButton
+                  shortcut={shortcut}
+                  command="test_swift"
+                  parameters={{
+                    folder: path,
+                    toolchainPath: selectedToolchain?.path ?? "",
+                    filter: [],
+                    onlyFailed: false,
+                    coverage: false,
+                  }}
+                  label="Run Tests"
+                  useMenuItem
+                  id="testMenuBtn"
+                />
+              );
+            },
+            componentId: "testMenuBtn",
+          },
+          {
+            name: "Re-run Failed Tests",
+            component: () => {
+              const { path } = useParams<"path">();
+              const { selectedToolchain } = useIDE();
+              return (
+                <CommandButton
+                  command="test_swift"
+                  parameters={{
+                    folder: path,
+                    toolchainPath: selectedToolchain?.path ?? "",
+                    filter: [],
+                    onlyFailed: true,
+                    coverage: false,
+                  }}
+                  label="Re-run Failed Tests"
+                  useMenuItem
+                  id="testFailedMenuBtn"
+                />
+              );
+            },
+            componentId: "testFailedMenuBtn",
+          },
+          {
+            name: "Run Tests with Coverage",
+            component: () => {
+              const { path } = useParams<"path">();
+              const { selectedToolchain } = useIDE();
+              return (
+                <CommandButton
+                  command="test_swift"
+                  parameters={{
+                    folder: path,
+                    toolchainPath: selectedToolchain?.path ?? "",
+                    filter: [],
+                    onlyFailed: false,
+                    coverage: true,
+                  }}
+                  label="Run Tests with Coverage"
+                  useMenuItem
+                  id="testCoverageMenuBtn"
+                />
+              );
+            },
+            componentId: "testCoverageMenuBtn",
+          },
+        ],
+      },
+      {
+        label: "Packages",
+        items: [
+          {
+            name: "Resolve Packages",
+            component: () => {
+              const { path } = useParams<"path">();
+              const { selectedToolchain } = useIDE();
+              return (
+                <CommandButton
+                  command="resolve_packages"
+                  parameters={{
+                    folder: path,
+                    toolchainPath: selectedToolchain?.path ?? "",
+                  }}
+                  label="Resolve Packages"
+                  useMenuItem
+                  id="resolvePackagesMenuBtn"
+                />
+              );
+            },
+            componentId: "resolvePackagesMenuBtn",
+          },
+          {
+            name: "Update Packages",
+            component: () => {
+              const { path } = useParams<"path">();
+              const { selectedToolchain } = useIDE();
+              return (
+                <CommandButton
+                  command="update_packages"
+                  parameters={{
+                    folder: path,
+                    toolchainPath: selectedToolchain?.path ?? "",
+                    packages: [],
+                  }}
+                  label="Update Packages"
+                  useMenuItem
+                  id="updatePackagesMenuBtn"
+                />
+              );
+            },
+            componentId: "updatePackagesMenuBtn",
+          },
+        ],
+      },
     ],
   },
   {
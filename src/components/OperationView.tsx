@@ -51,6 +51,8 @@ export default ({
             let completed = operationState.completed.includes(step.id);
             let started = operationState.started.includes(step.id);
             let notStarted = !failed && !completed && !started;
+            let progress = operationState.progress[step.id];
+            let duration = operationState.durations[step.id];
             return (
               <div className="operation-step">
                 <div className="operation-step-icon">
@@ -85,6 +87,18 @@ export default ({
                     textColor={notStarted ? "neutral.500" : undefined}
                   >
                     {step.title}
+                    {started && !completed && !failed && progress && (
+                      <Typography level="body-sm" textColor="neutral.500">
+                        {" "}
+                        ({progress.completed}/{progress.total})
+                      </Typography>
+                    )}
+                    {duration !== undefined && (
+                      <Typography level="body-sm" textColor="neutral.500">
+                        {" "}
+                        {(duration / 1000).toFixed(1)}s
+                      </Typography>
+                    )}
                   </Typography>
                   {failed && (
                     <Accordion sx={{ marginTop: 0 }}>
@@ -1,8 +1,8 @@
-import { Button, Typography } from "@mui/joy";
+import { Button, Checkbox, Typography } from "@mui/joy";
 import { useIDE } from "../utilities/IDEContext";
 import { open } from "@tauri-apps/plugin-dialog";
 import { useToast } from "react-toast-plus";
-import { useCallback, useEffect } from "react";
+import { useCallback, useEffect, useState } from "react";
 import { openUrl } from "@tauri-apps/plugin-opener";
 import { installSdkOperation } from "../utilities/operations";
 
@@ -16,31 +16,60 @@ export default () => {
     hasWSL,
   } = useIDE();
   const { addToast } = useToast();
+  const [offline, setOffline] = useState(false);
 
   const isWindowsReady = !isWindows || hasWSL;
 
-  const install = useCallback(async () => {
-    let xipPath = await open({
-      directory: false,
-      multiple: false,
-      filters: [
-        {
-          name: "XCode",
-          extensions: ["xip"],
-        },
-      ],
-    });
-    if (!xipPath) {
-      addToast.error("No Xcode.xip selected");
-      return;
-    }
-    const params = {
-      xcodePath: xipPath,
-      toolchainPath: selectedToolchain?.path || "",
-    };
-    await startOperation(installSdkOperation, params);
-    checkSDK();
-  }, [selectedToolchain, addToast]);
+  // An extracted Xcode.app or Contents/Developer directory skips extracting the .xip
+  const install = useCallback(
+    async (fromFolder: boolean) => {
+      let xcodePath = await open({
+        directory: fromFolder,
+        multiple: false,
+        filters: fromFolder
+          ? undefined
+          : [
+              {
+                name: "XCode",
+                extensions: ["xip"],
+              },
+            ],
+      });
+      if (!xcodePath) {
+        addToast.error(
+          fromFolder
+            ? "No Xcode.app or Developer folder selected"
+            : "No Xcode.xip selected"
+        );
+        return;
+      }
+      let toolsetPath = null;
+      if (offline) {
+        toolsetPath = await open({
+          directory: false,
+          multiple: false,
+          filters: [
+            {
+              name: "Toolset",
+              extensions: ["tar.gz", "tgz"],
+            },
+          ],
+        });
+        if (!toolsetPath) {
+          addToast.error("No toolset archive selected");
+          return;
+        }
+      }
+      const params = {
+        xcodePath,
+        toolchainPath: selectedToolchain?.path || "",
+        toolsetPath,
+      };
+      await startOperation(installSdkOperation, params);
+      checkSDK();
+    },
+    [selectedToolchain, addToast, offline]
+  );
 
   useEffect(() => {
     checkSDK();
@@ -83,13 +112,29 @@ export default () => {
         >
           Download XCode 16.3
         </Button>
-        <Button variant="soft" onClick={install} disabled={!selectedToolchain}>
+        <Button
+          variant="soft"
+          onClick={() => install(false)}
+          disabled={!selectedToolchain}
+        >
           {hasDarwinSDK ? "Reinstall SDK" : "Install SDK"}
         </Button>
+        <Button
+          variant="soft"
+          onClick={() => install(true)}
+          disabled={!selectedToolchain}
+        >
+          Install from Xcode.app...
+        </Button>
         <Button variant="soft" onClick={checkSDK} disabled={!selectedToolchain}>
           Check Again
         </Button>
       </div>
+      <Checkbox
+        label="Use a local toolset archive (offline)"
+        checked={offline}
+        onChange={(e) => setOffline(e.target.checked)}
+      />
     </div>
   );
 };
@@ -26,7 +26,9 @@ export default function Console() {
       (async () => {
         const unlistenFn = await listen("build-output", (event) => {
           let line = event.payload as string;
-          if (line.includes("command.done")) {
+          if (line === "command.cancelled") {
+            setConsoleLines((lines) => [...lines, "Command cancelled"]);
+          } else if (line.includes("command.done")) {
             if (line.split(".")[2] === "999") {
               setConsoleLines((lines) => [...lines, "Command failed"]);
             } else {
@@ -69,12 +69,12 @@ export function useCommandRunner() {
     } finally {
       setIsRunningCommand(true);
       setCurrentCommand(null);
-      // try {
-      //   await invoke("cancel_command");
-      // } finally {
-      setIsRunningCommand(false);
+      try {
+        await invoke("cancel_command");
+      } finally {
+        setIsRunningCommand(false);
+      }
     }
-    //}
   };
 
   return {
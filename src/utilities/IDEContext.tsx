@@ -44,6 +44,7 @@ export interface IDEContextType {
     operation: Operation,
     params: { [key: string]: any }
   ) => Promise<void>;
+  trackOperation: (operation: Operation) => Promise<() => void>;
   setSelectedToolchain: (
     value: Toolchain | ((oldValue: Toolchain | null) => Toolchain | null) | null
   ) => void;
@@ -277,62 +278,81 @@ export const IDEProvider: React.FC<{
     null
   );
 
-  const startOperation = useCallback(
-    async (
-      operation: Operation,
-      params: { [key: string]: any }
-    ): Promise<void> => {
+  // Shows the operation and follows its updates until the returned function is called
+  const trackOperation = useCallback(
+    async (operation: Operation): Promise<() => void> => {
       setOperationState({
         current: operation,
         started: [],
         failed: [],
         completed: [],
+        durations: {},
+        progress: {},
       });
-      return new Promise<void>(async (resolve, reject) => {
-        const unlistenFn = await listen<OperationUpdate>(
-          "operation_" + operation.id,
-          (event) => {
-            setOperationState((old) => {
-              if (old == null) return null;
-              if (event.payload.updateType === "started") {
-                return {
-                  ...old,
-                  started: [...old.started, event.payload.stepId],
-                };
-              } else if (event.payload.updateType === "finished") {
-                return {
-                  ...old,
-                  completed: [...old.completed, event.payload.stepId],
-                };
-              } else if (event.payload.updateType === "failed") {
-                return {
-                  ...old,
-                  failed: [
-                    ...old.failed,
-                    {
-                      stepId: event.payload.stepId,
-                      extraDetails: event.payload.extraDetails,
-                    },
-                  ],
-                };
-              }
-              return old;
-            });
-          }
-        );
-        try {
-          await invoke(operation.id + "_operation", params);
-          unlistenFn();
-          resolve();
-        } catch (e) {
-          unlistenFn();
-          reject(e);
+      return await listen<OperationUpdate>(
+        "operation_" + operation.id,
+        (event) => {
+          setOperationState((old) => {
+            if (old == null) return null;
+            const update = event.payload;
+            if (update.updateType === "started") {
+              return {
+                ...old,
+                started: [...old.started, update.stepId],
+              };
+            } else if (update.updateType === "progress") {
+              return {
+                ...old,
+                progress: { ...old.progress, [update.stepId]: update.progress },
+              };
+            } else if (update.updateType === "finished") {
+              return {
+                ...old,
+                completed: [...old.completed, update.stepId],
+                durations:
+                  update.durationMs == null
+                    ? old.durations
+                    : { ...old.durations, [update.stepId]: update.durationMs },
+              };
+            } else if (update.updateType === "failed") {
+              return {
+                ...old,
+                failed: [
+                  ...old.failed,
+                  {
+                    stepId: update.stepId,
+                    extraDetails: update.extraDetails,
+                  },
+                ],
+                durations:
+                  update.durationMs == null
+                    ? old.durations
+                    : { ...old.durations, [update.stepId]: update.durationMs },
+              };
+            }
+            return old;
+          });
         }
-      });
+      );
     },
     [setOperationState]
   );
 
+  const startOperation = useCallback(
+    async (
+      operation: Operation,
+      params: { [key: string]: any }
+    ): Promise<void> => {
+      const unlistenFn = await trackOperation(operation);
+      try {
+        await invoke(operation.id + "_operation", params);
+      } finally {
+        unlistenFn();
+      }
+    },
+    [trackOperation]
+  );
+
   const contextValue = useMemo(
     () => ({
       isWindows,
@@ -350,6 +370,7 @@ export const IDEProvider: React.FC<{
       hasDarwinSDK,
       checkSDK,
       startOperation,
+      trackOperation,
     }),
     [
       isWindows,
@@ -367,6 +388,7 @@ export const IDEProvider: React.FC<{
       hasDarwinSDK,
       checkSDK,
       startOperation,
+      trackOperation,
     ]
   );
 
@@ -17,20 +17,44 @@ export type OperationState = {
     stepId: string;
     extraDetails: string;
   }[];
+  durations: { [stepId: string]: number };
+  progress: { [stepId: string]: OperationProgress };
 };
 
-type OperationInfoUpdate = {
-  updateType: "started" | "finished";
+export type OperationProgress = {
+  completed: number;
+  total: number;
+};
+
+type OperationStartedUpdate = {
+  updateType: "started";
+  stepId: string;
+};
+
+type OperationFinishedUpdate = {
+  updateType: "finished";
+  stepId: string;
+  durationMs: number | null;
+};
+
+type OperationProgressUpdate = {
+  updateType: "progress";
   stepId: string;
+  progress: OperationProgress;
 };
 
 type OperationFailedUpdate = {
   updateType: "failed";
   stepId: string;
   extraDetails: string;
+  durationMs: number | null;
 };
 
-export type OperationUpdate = OperationInfoUpdate | OperationFailedUpdate;
+export type OperationUpdate =
+  | OperationStartedUpdate
+  | OperationFinishedUpdate
+  | OperationProgressUpdate
+  | OperationFailedUpdate;
 
 export const installSdkOperation: Operation = {
   id: "install_sdk",
@@ -42,7 +66,7 @@ export const installSdkOperation: Operation = {
     },
     {
       id: "install_toolset",
-      title: "Download & Install toolset",
+      title: "Install toolset",
     },
     {
       id: "extract_xip",
@@ -66,3 +90,62 @@ export const installSdkOperation: Operation = {
     },
   ],
 };
+
+const buildSteps: OperationStep[] = [
+  {
+    id: "load_config",
+    title: "Load Config",
+  },
+  {
+    id: "pre_build",
+    title: "Run pre_build Hook",
+  },
+  {
+    id: "compile",
+    title: "Compile",
+  },
+  {
+    id: "pack",
+    title: "Pack App",
+  },
+  {
+    id: "post_pack",
+    title: "Run post_pack Hook",
+  },
+];
+
+export const buildOperation: Operation = {
+  id: "build",
+  title: "Building App",
+  steps: [
+    ...buildSteps,
+    {
+      id: "zip",
+      title: "Create .ipa",
+    },
+  ],
+};
+
+export const deployOperation: Operation = {
+  id: "deploy",
+  title: "Building & Installing App",
+  steps: [
+    ...buildSteps,
+    {
+      id: "authenticate",
+      title: "Sign In to Apple ID",
+    },
+    {
+      id: "sign",
+      title: "Sign App",
+    },
+    {
+      id: "install",
+      title: "Install on Device",
+    },
+    {
+      id: "post_install",
+      title: "Run post_install Hook",
+    },
+  ],
+};